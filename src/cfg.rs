@@ -1,10 +1,12 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use syntax::ast::*;
 use report::*;
+use sat::{self, Formula};
+use targets::{self, Target};
 
 /// A `#[cfg]` attribute represented as a boolean expression tree.
-#[derive(PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq)]
 pub enum Config {
     // combinators
     Not(Box<Config>),
@@ -34,7 +36,7 @@ impl Config {
             true
         // no short-circuit if self.is_universal(): other may be like Any([True, False])
         } else {
-            !any(self, other, |vars| self.evaluate(vars) && !other.evaluate(vars))
+            !satisfiable(&[(self, true), (other, false)])
         }
     }
 
@@ -46,7 +48,7 @@ impl Config {
         if other.is_universal() || self.is_universal() {
             true
         } else {
-            any(self, other, |vars| self.evaluate(vars) && other.evaluate(vars))
+            satisfiable(&[(self, true), (other, true)])
         }
     }
 
@@ -55,7 +57,21 @@ impl Config {
         if self.is_universal() && other.is_universal() {
             true
         } else {
-            !any(self, other, |vars| self.evaluate(vars) ^ other.evaluate(vars))
+            !satisfiable(&[(self, true), (other, false)]) && !satisfiable(&[(self, false), (other, true)])
+        }
+    }
+
+    /// Determine if this Config applies when compiling for the given target
+    /// with the given features enabled. Other flags are taken to be unset.
+    pub fn applies(&self, target: &Target, features: &BTreeSet<String>) -> bool {
+        match *self {
+            Config::Not(ref inner) => !inner.applies(target, features),
+            Config::All(ref inner) => inner.iter().all(|i| i.applies(target, features)),
+            Config::Any(ref inner) => inner.iter().any(|i| i.applies(target, features)),
+            Config::TargetProperty(ref key, ref val) => target.has(key, val),
+            Config::Feature(ref name) => features.contains(name),
+            Config::True => true,
+            Config::Flag(_) | Config::False => false,
         }
     }
 
@@ -111,7 +127,7 @@ impl Config {
                     each.simplify();
                 }
                 inner.retain(|i| *i != Config::True);
-                if inner.len() == 0 {
+                if inner.is_empty() {
                     new_value = Config::True;
                 } else if inner.len() == 1 {
                     new_value = inner.remove(0);
@@ -124,7 +140,7 @@ impl Config {
                     each.simplify();
                 }
                 inner.retain(|i| *i != Config::False);
-                if inner.len() == 0 {
+                if inner.is_empty() {
                     new_value = Config::False;
                 } else if inner.len() == 1 {
                     new_value = inner.remove(0);
@@ -142,32 +158,45 @@ impl Config {
         *self == Config::True
     }
 
-    fn find_free_vars<'a>(&'a self, out: &mut BTreeSet<FreeVar<'a>>) {
-        match *self {
-            Config::Not(ref inner) => { inner.find_free_vars(out); }
-            Config::All(ref inner) | Config::Any(ref inner) => {
-                for i in inner { i.find_free_vars(out) }
+    /// Add this config to a formula, returning a literal which is true
+    /// exactly when the config applies.
+    fn encode<'a>(&'a self, formula: &mut Formula, vars: &mut BTreeMap<FreeVar<'a>, sat::Lit>) -> sat::Lit {
+        let var = match *self {
+            Config::Not(ref inner) => return inner.encode(formula, vars).negate(),
+            Config::All(ref inner) => {
+                let inner: Vec<sat::Lit> = inner.iter().map(|i| i.encode(formula, vars)).collect();
+                let out = formula.new_var();
+                // out <-> all(inner)
+                for &lit in &inner {
+                    formula.add_clause(vec![out.negate(), lit]);
+                }
+                let mut clause: Vec<sat::Lit> = inner.iter().map(|lit| lit.negate()).collect();
+                clause.push(out);
+                formula.add_clause(clause);
+                return out
             }
-            Config::TargetProperty(ref key, ref val) => {
-                out.insert(FreeVar::TargetProperty(key, val));
+            Config::Any(ref inner) => {
+                let inner: Vec<sat::Lit> = inner.iter().map(|i| i.encode(formula, vars)).collect();
+                let out = formula.new_var();
+                // out <-> any(inner)
+                for &lit in &inner {
+                    formula.add_clause(vec![lit.negate(), out]);
+                }
+                let mut clause = inner;
+                clause.push(out.negate());
+                formula.add_clause(clause);
+                return out
             }
-            Config::Feature(ref name) => { out.insert(FreeVar::Feature(name)); }
-            Config::Flag(ref name) => { out.insert(FreeVar::Flag(name)); }
-            Config::True | Config::False => {},
-        }
-    }
-
-    fn evaluate(&self, vars: &BTreeSet<&FreeVar>) -> bool {
-        match *self {
-            Config::Not(ref inner) => !inner.evaluate(vars),
-            Config::All(ref inner) => inner.iter().all(|i| i.evaluate(vars)),
-            Config::Any(ref inner) => inner.iter().any(|i| i.evaluate(vars)),
-            Config::TargetProperty(ref key, ref val) => vars.contains(&&FreeVar::TargetProperty(key, val)),
-            Config::Feature(ref name) => vars.contains(&&FreeVar::Feature(name)),
-            Config::Flag(ref name) => vars.contains(&&FreeVar::Flag(name)),
-            Config::True => true,
-            Config::False => false,
-        }
+            Config::True | Config::False => {
+                let out = formula.new_var();
+                formula.add_clause(vec![if *self == Config::True { out } else { out.negate() }]);
+                return out
+            }
+            Config::TargetProperty(ref key, ref val) => FreeVar::TargetProperty(key, val),
+            Config::Feature(ref name) => FreeVar::Feature(name),
+            Config::Flag(ref name) => FreeVar::Flag(name),
+        };
+        *vars.entry(var).or_insert_with(|| formula.new_var())
     }
 }
 
@@ -268,13 +297,9 @@ fn cfg_from_meta(r: &mut Report, attr: &MetaItemKind) -> Config {
                 "feature" => Config::Feature(string_lit),
                 "target_arch" | "target_os" | "target_family" |
                 "target_env" | "target_endian" | "target_pointer_width" |
-                "target_vendor" => {
+                "target_vendor" | "target_has_atomic" => {
                     Config::TargetProperty(string.to_string(), string_lit)
                 }
-                "target_has_atomic" => {
-                    // may be true for multiple values; treat like a flag
-                    Config::Flag(format!("target_has_atomic={:?}", string_lit))
-                }
                 _ => {
                     push!(r, Error, "Unknown #[cfg] key-value pair: {} = {:?}", string, lit.node);
                     Config::Flag(string.to_string())
@@ -284,57 +309,49 @@ fn cfg_from_meta(r: &mut Report, attr: &MetaItemKind) -> Config {
     }
 }
 
-fn any<F: Fn(&BTreeSet<&FreeVar>) -> bool>(one: &Config, other: &Config, f: F) -> bool {
-    // compute free var set
-    let mut free_vars = BTreeSet::new();
-    one.find_free_vars(&mut free_vars);
-    other.find_free_vars(&mut free_vars);
-
-    // turn free var set into series of options to iterate
-    let mut options: Vec<Vec<&FreeVar>> = vec![];
-    for var in &free_vars {
-        match *var {
-            FreeVar::TargetProperty(ref key, _) => {
-                match options.iter().position(|v| match *v[0] {
-                    FreeVar::TargetProperty(ref key2, _) if key == key2 => true,
-                    _ => false,
-                }) {
-                    Some(idx) => options[idx].push(var),
-                    None => options.push(vec![var]),
-                }
-            }
-            _ => options.push(vec![var]),
-        }
+/// Determine whether there is any situation in which each config applies
+/// or does not, as given.
+fn satisfiable(configs: &[(&Config, bool)]) -> bool {
+    let mut formula = Formula::new();
+    let mut vars = BTreeMap::new();
+    for &(config, applies) in configs {
+        let lit = config.encode(&mut formula, &mut vars);
+        formula.add_clause(vec![if applies { lit } else { lit.negate() }]);
     }
 
-    // iterate over each possibility
-    let mut positions = vec![0; options.len()];
-    let mut set = BTreeSet::new();
-    'outer: loop {
-        // evaluate the current set of positions
-        set.clear();
-        for (&pos, options) in positions.iter().zip(&options) {
-            if pos > 0 {
-                set.insert(options[pos - 1]);
+    // most target properties have at most one value, or possibly one not
+    // mentioned here
+    let properties: Vec<(&str, &str, sat::Lit)> = vars.iter().filter_map(|(var, &lit)| match *var {
+        FreeVar::TargetProperty(key, value) => Some((key, value, lit)),
+        _ => None,
+    }).collect();
+    for (idx, &(key, _, lit)) in properties.iter().enumerate() {
+        if targets::is_multi_valued(key) { continue }
+        for &(other_key, _, other_lit) in &properties[idx + 1..] {
+            if key == other_key {
+                formula.add_clause(vec![lit.negate(), other_lit.negate()]);
             }
         }
-        if f(&set) {
-            return true
-        }
+    }
 
-        // step to next set of positions, or break
-        for (pos, options) in positions.iter_mut().zip(&options) {
-            *pos += 1;
-            if *pos > options.len() {
-                *pos = 0;
-                continue;
+    // the properties must be those of some known target, unless a value no
+    // known target has is involved
+    if !properties.is_empty() {
+        let mut choices: Vec<sat::Lit> = properties.iter()
+            .filter(|&&(key, value, _)| !targets::is_known(key, value))
+            .map(|&(_, _, lit)| lit)
+            .collect();
+        for target in targets::TARGETS {
+            let chosen = formula.new_var();
+            for &(key, value, lit) in &properties {
+                formula.add_clause(vec![chosen.negate(), if target.has(key, value) { lit } else { lit.negate() }]);
             }
-            continue 'outer;
+            choices.push(chosen);
         }
-        break;
+        formula.add_clause(choices);
     }
 
-    false
+    formula.satisfiable()
 }
 
 #[derive(PartialEq, Eq, PartialOrd, Ord)]
@@ -357,3 +374,64 @@ fn oh_boy_here_we_go() {
     assert!(Config::Feature("one".into()).subset(&Config::Feature("one".into())));
     assert!(!Config::Feature("two".into()).subset(&Config::Feature("one".into())));
 }
+
+#[test]
+fn known_targets() {
+    fn target(key: &str, value: &str) -> Config {
+        Config::TargetProperty(key.into(), value.into())
+    }
+
+    // #[cfg(windows)] and #[cfg(target_os = "windows")]
+    assert!(target("target_family", "windows").intersects(&target("target_os", "windows")));
+    assert!(target("target_os", "windows").subset(&target("target_family", "windows")));
+    assert!(!target("target_family", "unix").subset(&target("target_os", "linux")));
+    assert!(!target("target_os", "windows").intersects(&target("target_family", "unix")));
+    assert!(!target("target_env", "msvc").intersects(&target("target_os", "linux")));
+    assert!(!target("target_vendor", "pc").intersects(&target("target_os", "macos")));
+    assert!(!target("target_arch", "x86_64").intersects(&target("target_pointer_width", "32")));
+
+    // several atomic sizes at once
+    assert!(target("target_has_atomic", "64").intersects(&target("target_has_atomic", "ptr")));
+    assert!(!target("target_has_atomic", "64").intersects(&target("target_arch", "mips")));
+
+    // unknown targets may have any combination
+    assert!(target("target_os", "redox").intersects(&target("target_family", "unix")));
+    assert!(!target("target_os", "redox").subset(&target("target_family", "unix")));
+    let neither = Config::Not(Box::new(Config::Any(vec![
+        target("target_family", "unix"),
+        target("target_family", "windows"),
+    ])));
+    assert!(!neither.intersects(&target("target_arch", "x86_64")));
+    assert!(neither.intersects(&target("target_os", "none")));
+}
+
+#[test]
+fn many_features() {
+    // enumerating assignments here would take 2^48 evaluations
+    let features: Vec<Config> = (0..48).map(|i| Config::Feature(format!("f{}", i))).collect();
+    let all = Config::All(features.clone());
+    let any = Config::Any(features.clone());
+    let pairs = Config::Any(features.chunks(2).map(|pair| Config::All(pair.to_vec())).collect());
+    let mut reversed = features.clone();
+    reversed.reverse();
+
+    assert!(all.subset(&any));
+    assert!(!any.subset(&all));
+    assert!(any.equivalent(&Config::Any(reversed)));
+    assert!(all.subset(&pairs));
+    assert!(pairs.subset(&any));
+    assert!(!any.subset(&pairs));
+    assert!(pairs.intersects(&Config::Not(Box::new(all.clone()))));
+    assert!(!all.intersects(&Config::Not(Box::new(pairs.clone()))));
+
+    let unix = Config::All(vec![
+        Config::TargetProperty("target_family".into(), "unix".into()),
+        pairs.clone(),
+    ]);
+    let windows = Config::All(vec![
+        Config::TargetProperty("target_family".into(), "windows".into()),
+        any.clone(),
+    ]);
+    assert!(!unix.intersects(&windows));
+    assert!(Config::Any(vec![unix.clone(), windows.clone()]).subset(&any));
+}
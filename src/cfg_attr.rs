@@ -0,0 +1,411 @@
+//! `#[cfg_attr]` expansion
+//!
+//! `#[cfg_attr(pred, attr)]` applies `attr` only when `pred` holds. Before
+//! macro expansion, each one on an item, field, variant, or trait, impl or
+//! foreign item is replaced according to what it wraps:
+//!
+//! - `cfg(x)` becomes `cfg(any(not(pred), x))`.
+//! - `derive(...)` is applied unconditionally so that the impls are
+//!   generated, and after expansion the impls are given `cfg(pred)`.
+//! - `path` changes what a module contains, so the module is duplicated:
+//!   one copy under `cfg(pred)` loaded from the other file, and one under
+//!   `cfg(not(pred))` as it was.
+//! - Anything else, such as `doc(hidden)` or `repr`, is applied
+//!   unconditionally, as there is no way to express it only applying in some
+//!   configurations.
+//!
+//! Only the built-in traits can be derived without the plugins which
+//! implement the others, so a derive of any other trait is replaced by an
+//! empty impl of it, bounded as a built-in derive would be.
+//!
+//! Out-of-line modules are loaded by the parser before any of this happens,
+//! so `mod foo;` must still have a `foo.rs` even if every configuration
+//! gives it a `path`.
+
+use std::mem;
+
+use syntax::ast::*;
+use syntax::attr::{self, AttrMetaMethods};
+use syntax::fold::{self, Folder};
+use syntax::parse::{self, token, ParseSess};
+use syntax::parse::token::InternedString;
+use syntax::ptr::P;
+use syntax::util::small_vector::SmallVector;
+
+/// Marks a derive which only applies under a predicate, until the impl it
+/// generates can be given the predicate.
+const DERIVE_MARKER: &'static str = "semverify_derive_cfg";
+
+/// Traits which `#[derive]` can expand without a plugin.
+const BUILTIN_DERIVES: &'static [&'static str] = &[
+    "Clone", "Copy", "Debug", "Default", "Eq", "Hash", "Ord", "PartialEq", "PartialOrd",
+    "Decodable", "Encodable", "RustcDecodable", "RustcEncodable",
+];
+
+/// Replace `#[cfg_attr]` on the crate's items, before macro expansion.
+pub fn expand_cfg_attrs(sess: &ParseSess, krate: Crate) -> Crate {
+    let config = krate.config.clone();
+    CfgAttrFolder { sess: sess, config: config }.fold_crate(krate)
+}
+
+/// Give impls produced by `#[derive]` the configuration of the type they
+/// were derived for, after macro expansion.
+pub fn configure_derives(krate: Crate) -> Crate {
+    DeriveFolder.fold_crate(krate)
+}
+
+struct CfgAttrFolder<'a> {
+    sess: &'a ParseSess,
+    config: CrateConfig,
+}
+
+impl<'a> CfgAttrFolder<'a> {
+    fn expand_item(&self, item: P<Item>) -> Vec<P<Item>> {
+        let mut conditional = Vec::new();
+        let mut custom = Vec::new();
+        let item = item.map(|mut item| {
+            item.attrs = expand_attrs(&item.attrs, &mut conditional);
+            custom = take_custom_derives(&mut item.attrs);
+            item
+        });
+        let derived: Vec<_> = custom.iter().filter_map(|name| self.custom_derive(&item, name)).collect();
+
+        let mut items = vec![item];
+        for (pred, inner) in conditional {
+            items = items.into_iter().flat_map(|item| vec![
+                with_cfg(self.apply(item.clone(), inner.clone()), pred.clone()),
+                with_cfg(item, list("not", vec![pred.clone()])),
+            ]).collect();
+        }
+        items.extend(derived);
+        items
+    }
+
+    /// Write out the impl a derive of a trait which is not built in would
+    /// produce: one for the type's parameters, bounded by the trait.
+    fn custom_derive(&self, item: &Item, trait_name: &str) -> Option<P<Item>> {
+        let generics = match item.node {
+            ItemKind::Struct(_, ref generics) | ItemKind::Enum(_, ref generics) => generics,
+            _ => return None,
+        };
+        let lifetimes: Vec<_> = generics.lifetimes.iter().map(|def| def.lifetime.name.to_string()).collect();
+        let params: Vec<_> = lifetimes.iter().cloned()
+            .chain(generics.ty_params.iter().map(|param| param.ident.name.to_string()))
+            .collect();
+        let bounded: Vec<_> = lifetimes.into_iter()
+            .chain(generics.ty_params.iter().map(|param| format!("{}: {}", param.ident.name, trait_name)))
+            .collect();
+        let source = format!("#[automatically_derived] impl<{}> {} for {}<{}> {{}}",
+            bounded.join(", "), trait_name, item.ident.name, params.join(", "));
+        match parse::parse_item_from_source_str("<derive>".to_owned(), source, self.config.clone(), self.sess) {
+            Ok(derived) => derived.map(|derived| derived.map(|mut derived| {
+                derived.span = item.span;
+                derived
+            })),
+            Err(mut e) => {
+                e.cancel();
+                None
+            }
+        }
+    }
+
+    /// Apply an attribute which was conditional to an item.
+    fn apply(&self, item: P<Item>, inner: P<MetaItem>) -> P<Item> {
+        let item = match inner.value_str() {
+            Some(ref path) if inner.check_name("path") => self.reload_module(item, path),
+            _ => item,
+        };
+        item.map(|mut item| {
+            item.attrs.push(attr::mk_attr_outer(attr::mk_attr_id(), inner));
+            item
+        })
+    }
+
+    /// Load an out-of-line module from a different file.
+    fn reload_module(&self, item: P<Item>, path: &str) -> P<Item> {
+        let codemap = self.sess.codemap();
+        let decl_file = codemap.span_to_filename(item.span);
+        let out_of_line = match item.node {
+            ItemKind::Mod(ref module) => codemap.span_to_filename(module.inner) != decl_file,
+            _ => false,
+        };
+        if !out_of_line {
+            return item
+        }
+        let file = ::std::path::Path::new(&decl_file).parent().unwrap_or_else(|| ::std::path::Path::new("")).join(path);
+        let mut parser = parse::new_sub_parser_from_file(self.sess, self.config.clone(), &file, true, None, item.span);
+        match parser.parse_crate_mod() {
+            Ok(krate) => item.map(move |mut item| {
+                item.attrs.extend(krate.attrs);
+                item.node = ItemKind::Mod(krate.module);
+                item
+            }),
+            Err(mut e) => {
+                e.emit();
+                item
+            }
+        }
+    }
+}
+
+impl<'a> Folder for CfgAttrFolder<'a> {
+    fn fold_item(&mut self, item: P<Item>) -> SmallVector<P<Item>> {
+        let items: Vec<_> = fold::noop_fold_item(item, self).into_iter().collect();
+        SmallVector::many(items.into_iter().flat_map(|item| self.expand_item(item)).collect())
+    }
+
+    fn fold_struct_field(&mut self, mut field: StructField) -> StructField {
+        field.attrs = expand_member_attrs(&field.attrs);
+        fold::noop_fold_struct_field(field, self)
+    }
+
+    fn fold_variant(&mut self, mut variant: Variant) -> Variant {
+        variant.node.attrs = expand_member_attrs(&variant.node.attrs);
+        fold::noop_fold_variant(variant, self)
+    }
+
+    fn fold_trait_item(&mut self, mut item: TraitItem) -> SmallVector<TraitItem> {
+        item.attrs = expand_member_attrs(&item.attrs);
+        fold::noop_fold_trait_item(item, self)
+    }
+
+    fn fold_impl_item(&mut self, mut item: ImplItem) -> SmallVector<ImplItem> {
+        item.attrs = expand_member_attrs(&item.attrs);
+        fold::noop_fold_impl_item(item, self)
+    }
+
+    fn fold_foreign_item(&mut self, mut item: ForeignItem) -> ForeignItem {
+        item.attrs = expand_member_attrs(&item.attrs);
+        fold::noop_fold_foreign_item(item, self)
+    }
+
+    fn fold_mac(&mut self, mac: Mac) -> Mac {
+        fold::noop_fold_mac(mac, self)
+    }
+}
+
+/// Replace the `#[cfg_attr]`s in a list of attributes, adding those which
+/// require the item to be duplicated to `conditional`.
+fn expand_attrs(attrs: &[Attribute], conditional: &mut Vec<(P<MetaItem>, P<MetaItem>)>) -> Vec<Attribute> {
+    let mut result = Vec::new();
+    for attr in attrs {
+        let parts = match split_cfg_attr(&attr.node.value) {
+            Some(parts) => parts,
+            None => {
+                result.push(attr.clone());
+                continue
+            }
+        };
+        for (pred, inner) in parts {
+            match (&*inner.name(), &inner.node) {
+                ("cfg", &MetaItemKind::List(_, ref items)) if items.len() == 1 => {
+                    let pred = list("any", vec![list("not", vec![pred]), items[0].clone()]);
+                    result.push(attr::mk_attr_outer(attr::mk_attr_id(), list("cfg", vec![pred])));
+                }
+                ("derive", &MetaItemKind::List(_, ref traits)) => {
+                    for name in traits.iter().map(|t| t.name()) {
+                        let marker = list(DERIVE_MARKER, vec![attr::mk_word_item(name), pred.clone()]);
+                        result.push(attr::mk_attr_outer(attr::mk_attr_id(), marker));
+                    }
+                    result.push(attr::mk_attr_outer(attr::mk_attr_id(), inner.clone()));
+                }
+                ("path", _) => conditional.push((pred, inner.clone())),
+                _ => result.push(attr::mk_attr_outer(attr::mk_attr_id(), inner.clone())),
+            }
+        }
+    }
+    result
+}
+
+/// Replace the `#[cfg_attr]`s on something other than an item. Only modules
+/// have a `path`, so nothing can require duplication.
+fn expand_member_attrs(attrs: &[Attribute]) -> Vec<Attribute> {
+    expand_attrs(attrs, &mut Vec::new())
+}
+
+/// Remove the traits which are not built in from `#[derive]`s, returning
+/// their names.
+fn take_custom_derives(attrs: &mut Vec<Attribute>) -> Vec<InternedString> {
+    let mut custom = Vec::new();
+    for attr in mem::replace(attrs, Vec::new()) {
+        let split = match attr.node.value.node {
+            MetaItemKind::List(ref name, ref traits) if &**name == "derive" => Some(traits.iter().cloned()
+                .partition::<Vec<_>, _>(|t| BUILTIN_DERIVES.contains(&&*t.name()))),
+            _ => None,
+        };
+        let (builtin, others) = match split {
+            Some(split) => split,
+            None => {
+                attrs.push(attr);
+                continue
+            }
+        };
+        if others.is_empty() {
+            attrs.push(attr);
+            continue
+        }
+        custom.extend(others.iter().map(|t| t.name()));
+        if !builtin.is_empty() {
+            attrs.push(attr::mk_attr_outer(attr::mk_attr_id(), list("derive", builtin)));
+        }
+    }
+    custom
+}
+
+/// Split `#[cfg_attr(pred, attrs...)]` into each attribute and the predicate
+/// it applies under.
+fn split_cfg_attr(meta: &MetaItem) -> Option<Vec<(P<MetaItem>, P<MetaItem>)>> {
+    match meta.node {
+        MetaItemKind::List(ref name, ref items) if &**name == "cfg_attr" && items.len() >= 2 => {
+            let pred = &items[0];
+            let mut parts = Vec::new();
+            for inner in &items[1..] {
+                match split_cfg_attr(inner) {
+                    // cfg_attr(a, cfg_attr(b, attr)) applies attr under all(a, b)
+                    Some(nested) => parts.extend(nested.into_iter()
+                        .map(|(nested_pred, attr)| (list("all", vec![pred.clone(), nested_pred]), attr))),
+                    None => parts.push((pred.clone(), inner.clone())),
+                }
+            }
+            Some(parts)
+        }
+        _ => None,
+    }
+}
+
+struct DeriveFolder;
+
+impl Folder for DeriveFolder {
+    fn fold_mod(&mut self, module: Mod) -> Mod {
+        let Mod { inner, items } = fold::noop_fold_mod(module, self);
+        let configs: Vec<_> = items.iter().map(|item| derived_impl_cfg(item, &items)).collect();
+        Mod {
+            inner: inner,
+            items: items.into_iter().zip(configs).map(|(item, config)| match config {
+                Some(pred) => with_cfg(item, pred),
+                None => item,
+            }).collect(),
+        }
+    }
+
+    fn fold_mac(&mut self, mac: Mac) -> Mac {
+        fold::noop_fold_mac(mac, self)
+    }
+}
+
+/// The predicate under which a derived impl applies: that of any of the
+/// type definitions it could be derived for, combined with the predicate
+/// of the derive itself.
+fn derived_impl_cfg(item: &Item, siblings: &[P<Item>]) -> Option<P<MetaItem>> {
+    if !item.attrs.iter().any(|a| a.check_name("automatically_derived")) {
+        return None
+    }
+    let (trait_name, self_name) = match item.node {
+        ItemKind::Impl(_, _, _, Some(ref trait_ref), ref self_ty, _) => {
+            match (trait_ref.path.segments.last(), &self_ty.node) {
+                (Some(trait_segment), &TyKind::Path(None, ref path)) => match path.segments.last() {
+                    Some(self_segment) => (trait_segment.identifier.name, self_segment.identifier.name),
+                    None => return None,
+                },
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+
+    let mut conditional = false;
+    let alternatives: Vec<_> = siblings.iter()
+        .filter(|sibling| sibling.ident.name == self_name)
+        .filter(|sibling| match sibling.node {
+            ItemKind::Struct(..) | ItemKind::Enum(..) => true,
+            _ => false,
+        })
+        .map(|sibling| {
+            let preds: Vec<_> = sibling.attrs.iter()
+                .filter_map(|attr| cfg_predicate(attr).or_else(|| derive_predicate(attr, trait_name)))
+                .collect();
+            conditional |= !preds.is_empty();
+            list("all", preds)
+        })
+        .collect();
+    if conditional {
+        Some(list("any", alternatives))
+    } else {
+        None
+    }
+}
+
+/// The predicate of a `#[cfg]` attribute.
+fn cfg_predicate(attr: &Attribute) -> Option<P<MetaItem>> {
+    match attr.node.value.node {
+        MetaItemKind::List(ref name, ref items) if &**name == "cfg" && items.len() == 1 => Some(items[0].clone()),
+        _ => None,
+    }
+}
+
+/// The predicate of a conditional derive of the given trait.
+fn derive_predicate(attr: &Attribute, trait_name: Name) -> Option<P<MetaItem>> {
+    match attr.node.value.node {
+        MetaItemKind::List(ref name, ref items) if &**name == DERIVE_MARKER && items.len() == 2 => {
+            if items[0].name() == trait_name.as_str() { Some(items[1].clone()) } else { None }
+        }
+        _ => None,
+    }
+}
+
+fn list(name: &str, items: Vec<P<MetaItem>>) -> P<MetaItem> {
+    attr::mk_list_item(token::intern_and_get_ident(name), items)
+}
+
+fn with_cfg(item: P<Item>, pred: P<MetaItem>) -> P<Item> {
+    item.map(|mut item| {
+        item.attrs.push(attr::mk_attr_outer(attr::mk_attr_id(), list("cfg", vec![pred])));
+        item
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use kind::ChangeKind::*;
+    use report::Severity::*;
+    use {compare_files, compare_sources, findings};
+
+    #[test]
+    fn conditional_derives() {
+        // the derived impl is only there when the derive applies
+        let r = compare_sources(
+            "#[derive(Clone)] pub struct Foo;",
+            "#[cfg_attr(unix, derive(Clone))] pub struct Foo;");
+        assert!(findings(&r).contains(&(CfgNarrowed, Major)));
+
+        // including derives of traits which need a plugin
+        let r = compare_sources(
+            "pub struct Foo<T>(pub T);",
+            "#[cfg_attr(feature = \"serde\", derive(Clone, Serialize))] pub struct Foo<T>(pub T);");
+        assert_eq!(findings(&r), vec![(ImplAdded, Breaking), (ImplAdded, Breaking)]);
+    }
+
+    #[test]
+    fn conditional_members() {
+        let r = compare_sources(
+            "pub trait Foo { fn foo(&self); }",
+            "pub trait Foo { #[cfg_attr(windows, cfg(any()))] fn foo(&self); }");
+        assert!(findings(&r).contains(&(CfgNarrowed, Major)));
+    }
+
+    #[test]
+    fn conditional_module_paths() {
+        let lib = ("src/lib.rs", "#[cfg_attr(unix, path = \"unix.rs\")] pub mod sys;");
+        let sys = ("src/sys.rs", "pub fn common() {}");
+
+        // the module is read from both files, and each compared in turn
+        let r = compare_files(
+            &[lib, sys, ("src/unix.rs", "pub fn common() {} pub fn raw_fd() {}")],
+            &[lib, sys, ("src/unix.rs", "pub fn common() {}")]);
+        assert_eq!(findings(&r), vec![(ItemRemoved, Major)]);
+
+        let unix = ("src/unix.rs", "pub fn common() {}");
+        let r = compare_files(&[lib, sys, unix], &[lib, ("src/sys.rs", ""), unix]);
+        assert_eq!(findings(&r), vec![(ItemRemoved, Major)]);
+    }
+}
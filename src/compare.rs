@@ -19,42 +19,76 @@
 // not covered by the RFC, and probably cannot be checked by a tool like this.
 // Instead, they should be caught using a comprehensive test suite.
 
+use std::collections::BTreeSet;
+
 use syntax::ast::*;
 use syntax::abi::Abi;
+use syntax::print::pprust;
 
 use report::*;
 use cfg::Config;
+use features::compare_features;
+use kind::ChangeKind;
+use impls::compare_impls;
+use macros::compare_macros;
+use resolve::{Namespaces, Module};
+use types::{self, TypeComparer, Relation};
+use ParsedCrate;
 
 fn todo(r: &mut Report, msg: &str) {
     push!(r, Debug, "TODO: {}", msg);
 }
 
+/// Information about both crates which is needed throughout the comparison.
+pub struct Context<'a> {
+    pub types: TypeComparer<'a>,
+    pub old_names: Namespaces<'a>,
+    pub new_names: Namespaces<'a>,
+    /// The crates being compared, for locating items in their sources
+    pub old: &'a ParsedCrate,
+    pub new: &'a ParsedCrate,
+}
+
 /// Generate a report on changes described in the "Crates" section.
-pub fn compare_crates(r: &mut Report, old: &Crate, new: &Crate) {
+pub fn compare_crates(r: &mut Report, old: &ParsedCrate, new: &ParsedCrate) {
+    let cx = Context {
+        types: TypeComparer::new(&old.krate, &new.krate),
+        old_names: Namespaces::new(&old.krate),
+        new_names: Namespaces::new(&new.krate),
+        old: old,
+        new: new,
+    };
+    let (old, new) = (&old.krate, &new.krate);
+
     let old_config = Config::new(r, &old.attrs);
     let new_config = Config::new(r, &new.attrs);
     if !old_config.subset(&new_config) {
-        push!(r, Major, "New crate reduces #[cfg] coverage\n  Was: {}\n  Now: {}", old_config, new_config);
+        push!(r, Major(CrateCfgNarrowed), "New crate reduces #[cfg] coverage\n  Was: {}\n  Now: {}", old_config, new_config);
     } else if !new_config.subset(&old_config) {
-        push!(r, Minor, "New crate increases #[cfg] coverage\n  Was: {}\n  Now: {}", old_config, new_config);
+        push!(r, Minor(CrateCfgWidened), "New crate increases #[cfg] coverage\n  Was: {}\n  Now: {}", old_config, new_config);
     }
     // TODO: Major: "going from stable to nightly"
     // - if `new` has #[feature(...)] but `old` does not
-    // TODO: Minor: "altering the use of Cargo features"
-    compare_macros(r, &old.exported_macros, &new.exported_macros);
-    compare_mods(r, &old.module, &new.module);
-}
-
-fn compare_macros(r: &mut Report, old: &[MacroDef], new: &[MacroDef]) {
-    // TODO: compare exported macros
+    if let (Some(old_features), Some(new_features)) = (cx.old.features.as_ref(), cx.new.features.as_ref()) {
+        compare_features(r, old_features, new_features);
+    }
+    compare_macros(r, &cx, &old.exported_macros, &new.exported_macros);
+    compare_mods(r, &cx, &cx.old_names.root(), &cx.new_names.root());
+    compare_impls(r, &cx, old, new);
 }
 
-fn compare_mods(r: &mut Report, old: &Mod, new: &Mod) {
+#[allow(unknown_lints, cyclomatic_complexity)]
+fn compare_mods<'a>(r: &mut Report, cx: &Context<'a>, old: &Module<'a>, new: &Module<'a>) {
     use syntax::ast::ItemKind::*;
     macro_rules! debug {
         ($($rest:tt)*) => { push!(r, Debug, $($rest)*) }
     }
 
+    // Items are looked up by the names they are visible under, including
+    // `pub use` re-exports, so that moving an item is not a removal as long
+    // as it is still re-exported at its old path.
+    let old_entries = cx.old_names.entries(old);
+    let new_entries = cx.new_names.entries(new);
     let mut child_mods = Vec::new();
 
     // TODO: include more detailed #[cfg] printouts in the messages
@@ -63,77 +97,111 @@ fn compare_mods(r: &mut Report, old: &Mod, new: &Mod) {
     // Check that every item is found at the same path in the new version, and
     // furthermore perform checks that the new item agrees with the old item
     // in an item-specific way.
-    for item in &old.items {
-        if !is_public(item) { continue }
+    for item in &old_entries {
+        if !item.is_public() { continue }
 
         macro_rules! find_item {
-            ($kind_name:expr; $closure:expr) => {{
+            ($kind_name:expr; |$r:ident, $new:ident| $body:expr) => {{
                 let kind = $kind_name;
-                let r = push!(r, Note, "{} {}", kind, item.ident.name);
-                let result = search_items(r, item, new.items.iter().map(|x| &**x), $closure);
+                let new_span = new_entries.iter().find(|e| e.name == item.name).map(|e| e.span);
+                let r = push!(r, Note, "{} {}", kind, item.name)
+                    .with_path(item_path(&old.path, item.name))
+                    .with_locations(cx.old.locate(item.span), new_span.and_then(|s| cx.new.locate(s)));
+                let result = search_items(r, item, &new_entries, |$r, $new| {
+                    // types in the items' signatures are relative to where they are defined
+                    cx.types.set_modules(&item.module, &$new.module);
+                    $body
+                });
                 if !result.found_name {
-                    push!(r, Major, "removed");
+                    push!(r, Major(ItemRemoved));
                 } else if !result.found_pub {
-                    push!(r, Major, "made private");
+                    push!(r, Major(MadePrivate));
+                } else if !result.found_kind {
+                    push!(r, Major(ItemKindChanged));
                 } else if !result.item_cfg.subset(&result.found_cfgs) {
-                    push!(r, Major, "availability narrowed:\n  Was: {}\n  Now: {}", result.item_cfg, result.found_cfgs);
+                    push!(r, Major(CfgNarrowed), "availability narrowed:\n  Was: {}\n  Now: {}", result.item_cfg, result.found_cfgs);
                 }
             }}
         }
 
-        match item.node {
+        match item.item.node {
             // Child module: push to list for later consumption (improves tree structure)
-            Mod(ref module) => find_item!("mod"; |r, new| if let Mod(ref new_module) = new.node {
+            Mod(ref module) => find_item!("mod"; |r, new| if let Mod(ref new_module) = new.item.node {
                 child_mods.push((item, module, new, new_module));
                 true
             } else { false }),
 
-            // TODO: Structs
+            // Structs
             // See: Signatures in type definitions
-            // Major: "adding a private field when all current fields are public."
-            // Major: "adding a public field when no private field exists."
-            // Minor: "adding or removing private fields when at least one already exists (before and after the change)."
-            // - "For tuple structs, this is only a minor change if furthermore all fields are currently private."
-            // Breaking: "going from a tuple struct with all private fields (with at least one field) to a normal struct, or vice versa."
+            Struct(ref data, ref generics) => find_item!("struct"; |r, new| if let Struct(ref new_data, ref new_generics) = new.node {
+                compare_type_generics(r, cx, generics, new_generics);
+                compare_struct_fields(r, cx, (data, generics), (new_data, new_generics));
+                true
+            } else { false }),
 
-            // TODO: Enums
+            // Enums
             // See: Signatures in type definitions
-            // Major: "adding new variants."
-            // - Merely Breaking if enum has indicated nonexaustiveness in a nonstandard way
-            // Major: "adding new fields to a variant."
+            Enum(ref def, ref generics) => find_item!("enum"; |r, new| if let Enum(ref new_def, ref new_generics) = new.node {
+                compare_type_generics(r, cx, generics, new_generics);
+                compare_enums(r, cx, (def, generics), (new_def, new_generics));
+                true
+            } else { false }),
 
-            // TODO: Traits
-            // Major: "adding a non-defaulted item."
-            // Major: "any non-trivial change to item signatures."
-            // Breaking: "adding a defaulted item."
-            // Minor: "adding a defaulted type parameter."
+            // Traits
+            Trait(unsafety, ref generics, ref bounds, ref items) => find_item!("trait"; |r, new| {
+                if let Trait(new_unsafety, ref new_generics, ref new_bounds, ref new_items) = new.node {
+                    compare_traits(r, cx,
+                        (unsafety, generics, &**bounds, &items[..]),
+                        (new_unsafety, new_generics, &**new_bounds, &new_items[..]));
+                    true
+                } else { false }
+            }),
 
-            // TODO: Trait implementations
-            // Major: "implementing any "fundamental" trait."
-            // Breaking: "implementing any non-fundamental trait."
+            // Trait and inherent implementations: see impls::compare_impls
+            Impl(..) | DefaultImpl(..) => {}
 
-            // TODO: Inherent implementations
-            // Breaking: "adding any inherent items."
+            // Re-exports from other crates
+            // Major: dropping a `pub use`, or changing what it refers to.
+            Use(..) => find_item!("use"; |r, new| match (&item.external, &new.external) {
+                (&Some(ref path), &Some(ref new_path)) => {
+                    if path != new_path {
+                        changed!(r, Major(ReexportChanged), "re-export of {}", (path => new_path), item.name);
+                    }
+                    true
+                }
+                _ => false,
+            }),
 
-            // TODO: Type aliases
+            // Type aliases
             // See: Signatures in type definitions
+            Ty(ref ty, ref generics) => find_item!("type"; |r, new| if let Ty(ref new_ty, ref new_generics) = new.node {
+                compare_type_generics(r, cx, generics, new_generics);
+                match cx.types.compare_generic((ty, generics), (new_ty, new_generics)) {
+                    Relation::Equal => {}
+                    Relation::MoreGeneral => {
+                        push!(r, Minor(TypeGeneralized), "type {} generalized:\n  Was: {:?}\n  Now: {:?}", item.ident, ty, new_ty);
+                    }
+                    _ => { changed!(r, Major(TypeChanged), "type {}'s definition", (ty => new_ty), item.ident); }
+                }
+                true
+            } else { false }),
 
             // Consts and statics
             Const(ref ty, _) => find_item!("const"; |r, new| match new.node {
                 Const(ref new_ty, _) => {
-                    if !types_equal(r, ty, new_ty) {
-                        changed!(r, Major, "const {}'s type", (ty => new_ty), item.ident);
+                    if cx.types.compare_static(ty, new_ty) != Relation::Equal {
+                        changed!(r, Major(TypeChanged), "const {}'s type", (ty => new_ty), item.ident);
                     }
                     true
                 }
                 Static(ref new_ty, mutability, _) => {
                     if mutability != Mutability::Immutable {
-                        push!(r, Major, "const {} replaced by static mut", item.ident);
+                        push!(r, Major(ConstReplacedByStatic), "const {} replaced by static mut", item.ident);
                     } else {
-                        push!(r, Minor, "const {} replaced by static", item.ident);
+                        push!(r, Minor(ConstReplacedByStatic), "const {} replaced by static", item.ident);
                     }
-                    if !types_equal(r, ty, new_ty) {
-                        changed!(r, Major, "const {}'s type", (ty => new_ty), item.ident);
+                    if cx.types.compare_static(ty, new_ty) != Relation::Equal {
+                        changed!(r, Major(TypeChanged), "const {}'s type", (ty => new_ty), item.ident);
                     }
                     true
                 }
@@ -141,18 +209,18 @@ fn compare_mods(r: &mut Report, old: &Mod, new: &Mod) {
             }),
             Static(ref ty, mutability, _) => find_item!("static"; |r, new| match new.node {
                 Const(ref new_ty, _) => {
-                    push!(r, Major, "static {} replaced by const", item.ident);
-                    if !types_equal(r, ty, new_ty) {
-                        changed!(r, Major, "static {}'s type", (ty => new_ty), item.ident);
+                    push!(r, Major(StaticReplacedByConst), "static {} replaced by const", item.ident);
+                    if cx.types.compare_static(ty, new_ty) != Relation::Equal {
+                        changed!(r, Major(TypeChanged), "static {}'s type", (ty => new_ty), item.ident);
                     }
                     true
                 }
                 Static(ref new_ty, new_mutability, _) => {
-                    if !types_equal(r, ty, new_ty) {
-                        changed!(r, Major, "static {}'s type", (ty => new_ty), item.ident);
+                    if cx.types.compare_static(ty, new_ty) != Relation::Equal {
+                        changed!(r, Major(TypeChanged), "static {}'s type", (ty => new_ty), item.ident);
                     }
                     if mutability != new_mutability {
-                        changed!(r, Major, "static {}'s mutability", (mutability => new_mutability), item.ident);
+                        changed!(r, Major(MutabilityChanged), "static {}'s mutability", (mutability => new_mutability), item.ident);
                     }
                     true
                 }
@@ -166,9 +234,9 @@ fn compare_mods(r: &mut Report, old: &Mod, new: &Mod) {
                 find_item!("fn"; |r, new| if let Fn(ref new_decl, new_unsafety, new_constness, new_abi, ref new_generics, _) = new.node {
                     let r = push!(r, Note, "fn {}", item.ident.name);
                     if constness == Constness::Const && new_constness == Constness::NotConst {
-                        push!(r, Major, "const qualifier removed");
+                        push!(r, Major(ConstQualifierRemoved));
                     }
-                    compare_functions(r,
+                    compare_functions(r, cx,
                         (decl, generics, unsafety, abi),
                         (new_decl, new_generics, new_unsafety, new_abi));
                     true
@@ -185,20 +253,23 @@ fn compare_mods(r: &mut Report, old: &Mod, new: &Mod) {
     // before. Due to glob imports, these are technically Breaking, but it
     // would impact report clarity to report this accurately. Instead, they
     // are reported as Minor.
-    for item in &new.items {
-        if !is_public(item) { continue }
+    for item in &new_entries {
+        if !item.is_public() { continue }
 
         macro_rules! find_item {
             ($kind_name:expr; $closure:expr) => {{
                 let kind = $kind_name;
-                let r = push!(r, Lazy Note, "{} {}", kind, item.ident.name);
-                let result = search_items(r, item, old.items.iter().map(|x| &**x), $closure);
+                let old_span = old_entries.iter().find(|e| e.name == item.name).map(|e| e.span);
+                let r = push!(r, Lazy Note, "{} {}", kind, item.name)
+                    .with_path(item_path(&new.path, item.name))
+                    .with_locations(old_span.and_then(|s| cx.old.locate(s)), cx.new.locate(item.span));
+                let result = search_items(r, item, &old_entries, $closure);
                 if !result.found_name {
-                    push!(r, Minor, "added");
+                    push!(r, Minor(ItemAdded));
                 } else if !result.found_pub {
-                    push!(r, Minor, "made public");
+                    push!(r, Minor(MadePublic));
                 } else if !result.item_cfg.subset(&result.found_cfgs) {
-                    push!(r, Minor, "availability widened:\n  Was: {}\n  Now: {}", result.found_cfgs, result.item_cfg);
+                    push!(r, Minor(CfgWidened), "availability widened:\n  Was: {}\n  Now: {}", result.found_cfgs, result.item_cfg);
                 }
             }}
         }
@@ -208,6 +279,21 @@ fn compare_mods(r: &mut Report, old: &Mod, new: &Mod) {
             Mod(ref module) => find_item!("mod"; |_, old| match old.node {
                 Mod(_) => true, _ => false,
             }),
+            // Structs and enums
+            Struct(..) => find_item!("struct"; |_, old| match old.node {
+                Struct(..) => true, _ => false,
+            }),
+            Enum(..) => find_item!("enum"; |_, old| match old.node {
+                Enum(..) => true, _ => false,
+            }),
+            // Traits
+            Trait(..) => find_item!("trait"; |_, old| match old.node {
+                Trait(..) => true, _ => false,
+            }),
+            // Type aliases
+            Ty(..) => find_item!("type"; |_, old| match old.node {
+                Ty(..) => true, _ => false,
+            }),
             // Consts and statics
             Const(ref ty, _) => find_item!("const"; |_, old| {
                 match old.node { Const(..) | Static(..) => true, _ => false }
@@ -219,21 +305,43 @@ fn compare_mods(r: &mut Report, old: &Mod, new: &Mod) {
             Fn(ref decl, unsafety, constness, abi, ref generics, _) => find_item!("fn"; |_, old| {
                 match old.node { Fn(..) => true, _ => false }
             }),
+            // Re-exports from other crates
+            Use(..) => find_item!("use"; |_, old| old.external.is_some()),
             _ => {}
         }
     }
 
     // Recurse to child modules
     for (item, module, new_item, new_module) in child_mods {
-        let r = push!(r, Note, "mod {}", item.ident.name);
-        let old_config = Config::new(r, &item.attrs);
+        let old_child = Module { path: item.child_path(), module: module, via: item.via.clone() };
+        let new_child = Module { path: new_item.child_path(), module: new_module, via: new_item.via.clone() };
+        // A module re-exported inside itself (e.g. by `pub use super::*`)
+        // has already been compared
+        if old.path.starts_with(&old_child.path) || new.path.starts_with(&new_child.path) {
+            continue
+        }
+        let r = push!(r, Note, "mod {}", item.name)
+            .with_path(item_path(&old.path, item.name))
+            .with_locations(cx.old.locate(item.span), cx.new.locate(new_item.span));
+        let old_config = item.config(r);
         old_config.report(r, &Config::True, "");
-        let r = Config::new(r, &new_item.attrs).report(r, &old_config, "Comparing with ");
-        compare_mods(r, module, new_module);
+        let r = new_item.config(r).report(r, &old_config, "Comparing with ");
+        compare_mods(r, cx, &old_child, &new_child);
+    }
+}
+
+/// The path of an item named `name` in the module at `module`.
+fn item_path(module: &[Name], name: Name) -> String {
+    let mut path = String::new();
+    for segment in module {
+        path.push_str(&segment.as_str());
+        path.push_str("::");
     }
+    path.push_str(&name.as_str());
+    path
 }
 
-// TODO: Signatures in type definitions
+// Signatures in type definitions
 // Major: "tightening bounds."
 // Minor: "loosening bounds."
 // Minor: "adding defaulted type parameters."
@@ -241,16 +349,400 @@ fn compare_mods(r: &mut Report, old: &Mod, new: &Mod) {
 // - Minor: Foo(pub u8) to Foo<T = u8>(pub T)
 // - Major: Foo<T = u8>(pub T, pub u8) to Foo<T = u8>(pub T, pub T)
 // - Minor: Foo<T>(pub T, pub T) to Foo<T, U = T>(pub T, pub U)
-fn compare_type_generics(r: &mut Report, old: &Generics, new: &Generics) {
-    // TODO
+fn compare_type_generics(r: &mut Report, cx: &Context, old: &Generics, new: &Generics) {
+    if old.lifetimes.len() != new.lifetimes.len() {
+        push!(r, Major(LifetimeParamsChanged), "lifetime parameter count has changed:\n  Was: {}\n  Now: {}", old.lifetimes.len(), new.lifetimes.len());
+    }
+
+    // Type parameters are matched by position
+    for (idx, param) in old.ty_params.iter().enumerate() {
+        let new_param = match new.ty_params.get(idx) {
+            Some(new_param) => new_param,
+            None => {
+                push!(r, Major(TypeParamRemoved), "type parameter {} removed", param.ident);
+                continue
+            }
+        };
+        match (&param.default, &new_param.default) {
+            (&Some(_), &None) => { push!(r, Major(TypeParamDefaultRemoved), "type parameter {}'s default removed", param.ident); }
+            (&None, &Some(_)) => { push!(r, Minor(TypeParamDefaultAdded), "type parameter {}'s default added", param.ident); }
+            (&Some(ref default), &Some(ref new_default)) => {
+                if cx.types.compare_generic((default, old), (new_default, new)) != Relation::Equal {
+                    changed!(r, Major(TypeParamDefaultChanged), "type parameter {}'s default", (default => new_default), param.ident);
+                }
+            }
+            (&None, &None) => {}
+        }
+
+        let old_bounds = param_bounds(old, param.ident.name, &|path| cx.types.resolve_old_here(path));
+        let new_bounds = param_bounds(new, new_param.ident.name, &|path| cx.types.resolve_new_here(path));
+        let tightened: Vec<_> = new_bounds.difference(&old_bounds).collect();
+        let loosened: Vec<_> = old_bounds.difference(&new_bounds).collect();
+        if !tightened.is_empty() {
+            push!(r, Major(BoundsTightened), "bounds on type parameter {} tightened: {:?}", param.ident, tightened);
+        }
+        if !loosened.is_empty() {
+            push!(r, Minor(BoundsLoosened), "bounds on type parameter {} loosened: {:?}", param.ident, loosened);
+        }
+    }
+    for param in new.ty_params.iter().skip(old.ty_params.len()) {
+        if param.default.is_some() {
+            push!(r, Minor(DefaultedTypeParamAdded), "defaulted type parameter {} added", param.ident);
+        } else {
+            push!(r, Major(TypeParamAdded), "type parameter {} added", param.ident);
+        }
+    }
 }
 
-fn types_equal(r: &mut Report, lhs: &Ty, rhs: &Ty) -> bool {
-    // TODO: a more robust comparison not based on pretty-printing
-    format!("{:?}", lhs) == format!("{:?}", rhs)
+// Structs
+// Major: "adding a private field when all current fields are public."
+// Major: "adding a public field when no private field exists."
+// Minor: "adding or removing private fields when at least one already exists (before and after the change)."
+// - "For tuple structs, this is only a minor change if furthermore all fields are currently private."
+// Breaking: "going from a tuple struct with all private fields (with at least one field) to a normal struct, or vice versa."
+fn compare_struct_fields(r: &mut Report, cx: &Context,
+    (old, generics): (&VariantData, &Generics),
+    (new, new_generics): (&VariantData, &Generics))
+{
+    let old_fields = old.fields();
+    let new_fields = new.fields();
+    let old_private = old_fields.iter().filter(|f| !field_is_public(f)).count();
+    let new_private = new_fields.iter().filter(|f| !field_is_public(f)).count();
+
+    if variant_kind(old) != variant_kind(new) {
+        // Only tuple <-> normal flips can be all-private with at least one field
+        if old_private == old_fields.len() && new_private == new_fields.len() &&
+            !old_fields.is_empty() && !new_fields.is_empty()
+        {
+            push!(r, Breaking(StructKindChanged), "struct kind has changed:\n  Was: {}\n  Now: {}", variant_kind(old), variant_kind(new));
+        } else {
+            push!(r, Major(StructKindChanged), "struct kind has changed:\n  Was: {}\n  Now: {}", variant_kind(old), variant_kind(new));
+        }
+        return
+    }
+
+    // Public fields must be retained with the same type
+    for (idx, field) in old_fields.iter().enumerate() {
+        if !field_is_public(field) { continue }
+        let r = push!(r, Lazy Note, "field {}", field_name(idx, field));
+        match find_field(new_fields, idx, field) {
+            None => { push!(r, Major(FieldRemoved), "removed"); }
+            Some(new_field) if !field_is_public(new_field) => { push!(r, Major(MadePrivate)); }
+            Some(new_field) => compare_field_types(r, cx, idx, (field, generics), (new_field, new_generics)),
+        }
+    }
+
+    // New public fields
+    for (idx, field) in new_fields.iter().enumerate() {
+        if !field_is_public(field) { continue }
+        let was_public = find_field(old_fields, idx, field)
+            .map_or(false, field_is_public);
+        if was_public { continue }
+        if old_private == 0 {
+            push!(r, Major(PublicFieldAdded), "public field {} added to struct with no private fields", field_name(idx, field));
+        } else {
+            push!(r, Minor(PublicFieldAdded), "public field {} added", field_name(idx, field));
+        }
+    }
+
+    // Private field churn, not counting fields already reported as made private
+    let made_private = |(idx, field): (usize, &StructField)| {
+        !field_is_public(field) && find_field(old_fields, idx, field).map_or(false, field_is_public)
+    };
+    let new_private = new_private - new_fields.iter().enumerate().filter(|&f| made_private(f)).count();
+    let private_changed = if old.is_tuple() {
+        old_private != new_private
+    } else {
+        let names = |fields: Vec<&StructField>| -> Vec<Name> {
+            let mut v: Vec<Name> = fields.iter()
+                .filter_map(|f| f.ident.map(|i| i.name))
+                .collect();
+            v.sort();
+            v
+        };
+        names(old_fields.iter().filter(|f| !field_is_public(f)).collect()) !=
+            names(new_fields.iter().enumerate()
+                .filter(|&f| !field_is_public(f.1) && !made_private(f))
+                .map(|(_, f)| f)
+                .collect())
+    };
+    if private_changed {
+        if old_private == 0 {
+            push!(r, Major(PrivateFieldsChanged), "private field added to struct with all public fields");
+        } else if old.is_tuple() && old_private != old_fields.len() {
+            // positions of the public fields may have shifted
+            push!(r, Major(PrivateFieldsChanged), "private fields added or removed in tuple struct with public fields");
+        } else {
+            push!(r, Minor(PrivateFieldsChanged));
+        }
+    }
+}
+
+// Enums
+// Major: "adding new variants."
+// - Merely Breaking if enum has indicated nonexaustiveness in a nonstandard way
+// Major: "adding new fields to a variant."
+fn compare_enums(r: &mut Report, cx: &Context,
+    (old, generics): (&EnumDef, &Generics),
+    (new, new_generics): (&EnumDef, &Generics))
+{
+    let nonexhaustive = old.variants.iter().any(|v| is_nonexhaustive_marker(old, v));
+
+    for variant in &old.variants {
+        if is_nonexhaustive_marker(old, variant) { continue }
+        let r = push!(r, Lazy Note, "variant {}", variant.node.name);
+        let result = search_items(r, variant, &new.variants, |r, new_variant| {
+            compare_variant_fields(r, cx, (&variant.node.data, generics), (&new_variant.node.data, new_generics));
+            true
+        });
+        if !result.found_name {
+            push!(r, Major(ItemRemoved));
+        } else if !result.item_cfg.subset(&result.found_cfgs) {
+            push!(r, Major(CfgNarrowed), "availability narrowed:\n  Was: {}\n  Now: {}", result.item_cfg, result.found_cfgs);
+        }
+    }
+
+    for variant in &new.variants {
+        let r = push!(r, Lazy Note, "variant {}", variant.node.name);
+        let result = search_items(r, variant, &old.variants, |_, _| true);
+        if result.found_name && result.item_cfg.subset(&result.found_cfgs) {
+            continue
+        }
+        let (kind, what) = if !result.found_name { (ChangeKind::VariantAdded, "added".to_owned()) } else {
+            (ChangeKind::CfgWidened, format!("availability widened:\n  Was: {}\n  Now: {}", result.found_cfgs, result.item_cfg))
+        };
+        if nonexhaustive {
+            push!(r, Breaking[kind], "{} (enum is marked as nonexhaustive)", what);
+        } else {
+            push!(r, Major[kind], what);
+        }
+    }
+}
+
+// All fields of enum variants are public, so the rules are simpler than for
+// structs: any change to the fields is Major.
+fn compare_variant_fields(r: &mut Report, cx: &Context,
+    (old, generics): (&VariantData, &Generics),
+    (new, new_generics): (&VariantData, &Generics))
+{
+    if variant_kind(old) != variant_kind(new) {
+        push!(r, Major(VariantKindChanged), "variant kind has changed:\n  Was: {}\n  Now: {}", variant_kind(old), variant_kind(new));
+        return
+    }
+
+    let old_fields = old.fields();
+    let new_fields = new.fields();
+    for (idx, field) in old_fields.iter().enumerate() {
+        match find_field(new_fields, idx, field) {
+            None => { push!(r, Major(FieldRemoved), "field {} removed", field_name(idx, field)); }
+            Some(new_field) => compare_field_types(r, cx, idx, (field, generics), (new_field, new_generics)),
+        }
+    }
+    for (idx, field) in new_fields.iter().enumerate() {
+        if find_field(old_fields, idx, field).is_none() {
+            push!(r, Major(FieldAdded), "field {} added", field_name(idx, field));
+        }
+    }
 }
 
-// TODO: Signatures in functions
+/// Compare the types of a field which is public in both versions.
+fn compare_field_types(r: &mut Report, cx: &Context, idx: usize,
+    (old, generics): (&StructField, &Generics),
+    (new, new_generics): (&StructField, &Generics))
+{
+    match cx.types.compare_generic((&old.ty, generics), (&new.ty, new_generics)) {
+        Relation::Equal => {}
+        // Foo(pub u8) to Foo<T = u8>(pub T)
+        Relation::MoreGeneral => {
+            push!(r, Minor(TypeGeneralized), "field {}'s type generalized:\n  Was: {:?}\n  Now: {:?}", field_name(idx, old), old.ty, new.ty);
+        }
+        _ => { changed!(r, Major(TypeChanged), "field {}'s type", (old.ty => new.ty), field_name(idx, old)); }
+    }
+}
+
+/// Detect the nonstandard `#[doc(hidden)] __Nonexhaustive` variant convention:
+/// a variant named with a leading `__`, or a hidden last variant.
+fn is_nonexhaustive_marker(def: &EnumDef, variant: &Variant) -> bool {
+    let is_last = def.variants.last().map_or(false, |last| last.node.name.name == variant.node.name.name);
+    variant.node.name.name.as_str().starts_with("__") ||
+        (is_last && variant.node.attrs.iter().any(is_doc_hidden))
+}
+
+fn is_doc_hidden(attr: &Attribute) -> bool {
+    match attr.node.value.node {
+        MetaItemKind::List(ref name, ref items) if &**name == "doc" => items.iter().any(|i| match i.node {
+            MetaItemKind::Word(ref word) => &**word == "hidden",
+            _ => false,
+        }),
+        _ => false,
+    }
+}
+
+// Traits
+// Major: "adding a non-defaulted item."
+// Major: "any non-trivial change to item signatures."
+// Breaking: "adding a defaulted item."
+// Minor: "adding a defaulted type parameter."
+fn compare_traits(r: &mut Report, cx: &Context,
+    (unsafety, generics, bounds, items): (Unsafety, &Generics, &[TyParamBound], &[TraitItem]),
+    (new_unsafety, new_generics, new_bounds, new_items): (Unsafety, &Generics, &[TyParamBound], &[TraitItem]))
+{
+    if unsafety != new_unsafety {
+        changed!(r, Major(TraitUnsafetyChanged), "unsafety", (unsafety => new_unsafety));
+    }
+
+    compare_type_generics(r, cx, generics, new_generics);
+
+    // Adding a supertrait breaks implementors, removing one breaks users
+    let old_supers = resolved_bounds(bounds, &|path| cx.types.resolve_old_here(path));
+    let new_supers = resolved_bounds(new_bounds, &|path| cx.types.resolve_new_here(path));
+    for removed in old_supers.difference(&new_supers) {
+        push!(r, Major(SupertraitRemoved), "supertrait {} removed", removed);
+    }
+    for added in new_supers.difference(&old_supers) {
+        push!(r, Major(SupertraitAdded), "supertrait {} added", added);
+    }
+
+    for item in items {
+        let r = push!(r, Lazy Note, "{} {}", trait_item_kind(item), item.ident.name);
+        let result = search_items(r, item, new_items, |r, new_item| compare_trait_items(r, cx, item, new_item));
+        if !result.found_kind {
+            push!(r, Major(ItemRemoved));
+        } else if !result.item_cfg.subset(&result.found_cfgs) {
+            push!(r, Major(CfgNarrowed), "availability narrowed:\n  Was: {}\n  Now: {}", result.item_cfg, result.found_cfgs);
+        }
+    }
+
+    for item in new_items {
+        let r = push!(r, Lazy Note, "{} {}", trait_item_kind(item), item.ident.name);
+        let result = search_items(r, item, items, |_, old_item| trait_item_kind(old_item) == trait_item_kind(item));
+        if result.found_kind && result.item_cfg.subset(&result.found_cfgs) {
+            continue
+        }
+        if trait_item_has_default(item) {
+            push!(r, Breaking(DefaultedTraitItemAdded));
+        } else {
+            push!(r, Major(TraitItemAdded));
+        }
+    }
+}
+
+/// Compare two trait items of the same name, returning whether they are of
+/// the same kind.
+fn compare_trait_items(r: &mut Report, cx: &Context, old: &TraitItem, new: &TraitItem) -> bool {
+    use syntax::ast::TraitItemKind::*;
+    match (&old.node, &new.node) {
+        (&Const(ref ty, ref default), &Const(ref new_ty, ref new_default)) => {
+            if cx.types.compare(ty, new_ty) != Relation::Equal {
+                changed!(r, Major(TypeChanged), "const {}'s type", (ty => new_ty), old.ident);
+            }
+            compare_defaults(r, default.is_some(), new_default.is_some());
+        }
+        (&Method(ref sig, ref body), &Method(ref new_sig, ref new_body)) => {
+            {
+                let r = push!(r, Lazy Note, "signature");
+                compare_functions(r, cx,
+                    (&sig.decl, &sig.generics, sig.unsafety, sig.abi),
+                    (&new_sig.decl, &new_sig.generics, new_sig.unsafety, new_sig.abi));
+                // Note: In trait definitons, ALL signature changes are Major.
+                r.escalate_changes();
+            }
+            compare_defaults(r, body.is_some(), new_body.is_some());
+        }
+        (&Type(ref bounds, ref default), &Type(ref new_bounds, ref new_default)) => {
+            let old_bounds = resolved_bounds(bounds, &|path| cx.types.resolve_old_here(path));
+            let new_bounds = resolved_bounds(new_bounds, &|path| cx.types.resolve_new_here(path));
+            if old_bounds != new_bounds {
+                changed!(r, Major(AssocTypeBoundsChanged), "type {}'s bounds", (old_bounds => new_bounds), old.ident);
+            }
+            compare_defaults(r, default.is_some(), new_default.is_some());
+        }
+        _ => return false
+    }
+    true
+}
+
+fn compare_defaults(r: &mut Report, old: bool, new: bool) {
+    if old && !new {
+        push!(r, Major(TraitItemDefaultRemoved));
+    } else if !old && new {
+        push!(r, Minor(TraitItemDefaultAdded));
+    }
+}
+
+fn trait_item_kind(item: &TraitItem) -> &'static str {
+    match item.node {
+        TraitItemKind::Const(..) => "const",
+        TraitItemKind::Method(..) => "fn",
+        TraitItemKind::Type(..) => "type",
+        TraitItemKind::Macro(..) => "macro",
+    }
+}
+
+fn trait_item_has_default(item: &TraitItem) -> bool {
+    match item.node {
+        TraitItemKind::Const(_, ref default) => default.is_some(),
+        TraitItemKind::Method(_, ref body) => body.is_some(),
+        TraitItemKind::Type(_, ref default) => default.is_some(),
+        TraitItemKind::Macro(..) => false,
+    }
+}
+
+/// Print each of a list of bounds, for order-insensitive comparison, with
+/// traits named by the paths `resolve` gives them, so that bounds which are
+/// only spelled differently are the same.
+pub fn resolved_bounds(bounds: &[TyParamBound], resolve: &Fn(&Path) -> Vec<String>) -> BTreeSet<String> {
+    bounds.iter().map(|bound| match *bound {
+        TraitTyParamBound(ref poly, modifier) => {
+            let for_lifetimes: Vec<_> = poly.bound_lifetimes.iter().map(|def| def.lifetime.name.to_string()).collect();
+            format!("{}{}{}",
+                if for_lifetimes.is_empty() { String::new() } else { format!("for<{}> ", for_lifetimes.join(", ")) },
+                if modifier == TraitBoundModifier::Maybe { "?" } else { "" },
+                resolved_trait(&poly.trait_ref.path, resolve))
+        }
+        RegionTyParamBound(ref lifetime) => lifetime.name.to_string(),
+    }).collect()
+}
+
+/// Print a trait's resolved path, followed by its parameters as written.
+fn resolved_trait(path: &Path, resolve: &Fn(&Path) -> Vec<String>) -> String {
+    let params = path.segments.last().map_or(String::new(), |segment| {
+        let last = Path { span: path.span, global: false, segments: vec![segment.clone()] };
+        pprust::path_to_string(&last)[segment.identifier.name.as_str().len()..].to_owned()
+    });
+    resolve(path).join("::") + &params
+}
+
+/// Find the field corresponding to `field` (at position `idx`) in `fields`.
+///
+/// Tuple fields are matched by position, named fields by name.
+fn find_field<'a>(fields: &'a [StructField], idx: usize, field: &StructField) -> Option<&'a StructField> {
+    match field.ident {
+        Some(ident) => fields.iter().find(|f| f.ident.map(|i| i.name) == Some(ident.name)),
+        None => fields.get(idx).and_then(|f| if f.ident.is_none() { Some(f) } else { None }),
+    }
+}
+
+fn field_is_public(field: &StructField) -> bool {
+    field.vis == Visibility::Public
+}
+
+fn field_name(idx: usize, field: &StructField) -> String {
+    match field.ident {
+        Some(ident) => ident.name.to_string(),
+        None => idx.to_string(),
+    }
+}
+
+fn variant_kind(data: &VariantData) -> &'static str {
+    match *data {
+        VariantData::Struct(..) => "normal",
+        VariantData::Tuple(..) => "tuple",
+        VariantData::Unit(..) => "unit",
+    }
+}
+
+// Signatures in functions
 // Note: In trait definitons, ALL the following changes are Major.
 // Major: "adding/removing arguments."
 // Breaking: "introducing a new type parameter."
@@ -259,62 +751,228 @@ fn types_equal(r: &mut Report, lhs: &Ty, rhs: &Ty) -> bool {
 // - Major: if the generic has a bound not satisfied by the original type.
 // - Can in principle cause type inference failures, but left Minor.
 // - Minor: foo(_: &Trait) to foo<T: Trait + ?Sized>(t: &T)
-fn compare_functions(r: &mut Report,
+pub fn compare_functions(r: &mut Report, cx: &Context,
     (decl, generics, unsafety, abi): (&FnDecl, &Generics, Unsafety, Abi),
     (new_decl, new_generics, new_unsafety, new_abi): (&FnDecl, &Generics, Unsafety, Abi))
 {
     if unsafety != new_unsafety {
-        changed!(r, Major, "unsafety", (unsafety => new_unsafety));
+        changed!(r, Major(FnUnsafetyChanged), "unsafety", (unsafety => new_unsafety));
     }
     if abi != new_abi {
-        changed!(r, Major, "abi", (abi => new_abi));
+        changed!(r, Major(FnAbiChanged), "abi", (abi => new_abi));
+    }
+
+    // Elided lifetimes are named, so that they match the same lifetimes
+    // written out
+    let (sig, sig_generics) = types::elaborate_lifetimes(decl, generics);
+    let (new_sig, new_sig_generics) = types::elaborate_lifetimes(new_decl, new_generics);
+
+    // Arguments
+    let mut generalized = BTreeSet::new();
+    if decl.inputs.len() != new_decl.inputs.len() {
+        push!(r, Major(ArgCountChanged), "argument count has changed:\n  Was: {}\n  Now: {}", decl.inputs.len(), new_decl.inputs.len());
+    } else {
+        for (idx, (arg, new_arg)) in decl.inputs.iter().zip(&new_decl.inputs).enumerate() {
+            let (sig_arg, new_sig_arg) = (&sig.inputs[idx], &new_sig.inputs[idx]);
+            match cx.types.compare_generic((&sig_arg.ty, &sig_generics), (&new_sig_arg.ty, &new_sig_generics)) {
+                Relation::Equal => {}
+                Relation::MoreGeneral => match generalized_param(&arg.ty, generics, &new_arg.ty, new_generics) {
+                    Some((param, orig_ty)) => {
+                        generalized.insert(param);
+                        compare_generalization(r, cx, idx, param, orig_ty, new_generics);
+                    }
+                    None => {
+                        // e.g. Vec<u8> to Vec<T>
+                        for param in new_generics.ty_params.iter().skip(generics.ty_params.len()) {
+                            if mentions(&new_arg.ty, param.ident.name) {
+                                generalized.insert(param.ident.name);
+                            }
+                        }
+                        push!(r, Minor(ArgGeneralized), "argument {}'s type generalized:\n  Was: {:?}\n  Now: {:?}", idx, arg.ty, new_arg.ty);
+                    }
+                },
+                _ => { changed!(r, Major(ArgTypeChanged), "argument {}'s type", (arg.ty => new_arg.ty), idx); }
+            }
+        }
+    }
+
+    // Return type
+    match (&decl.output, &new_decl.output, &sig.output, &new_sig.output) {
+        (&FunctionRetTy::Ty(ref ty), &FunctionRetTy::Ty(ref new_ty), &FunctionRetTy::Ty(ref sig_ty), &FunctionRetTy::Ty(ref new_sig_ty)) => {
+            if cx.types.compare_generic((sig_ty, &sig_generics), (new_sig_ty, &new_sig_generics)) != Relation::Equal {
+                changed!(r, Major(ReturnTypeChanged), "return type", (ty => new_ty));
+            }
+        }
+        (&FunctionRetTy::Ty(_), _, _, _) | (_, &FunctionRetTy::Ty(_), _, _) => {
+            changed!(r, Major(ReturnTypeChanged), "return type", (decl.output => new_decl.output));
+        }
+        _ => {}
+    }
+
+    // Type parameters, matched by position so that renaming one is harmless
+    for (idx, param) in generics.ty_params.iter().enumerate() {
+        let name = param.ident.name;
+        let new_name = match new_generics.ty_params.get(idx) {
+            Some(new_param) => new_param.ident.name,
+            None => {
+                push!(r, Major(TypeParamRemoved), "type parameter {} removed", name);
+                continue
+            }
+        };
+        let old_bounds = param_bounds(generics, name, &|path| cx.types.resolve_old_here(path));
+        let new_bounds = param_bounds(new_generics, new_name, &|path| cx.types.resolve_new_here(path));
+        let tightened: Vec<_> = new_bounds.difference(&old_bounds).collect();
+        let loosened: Vec<_> = old_bounds.difference(&new_bounds).collect();
+        if !tightened.is_empty() {
+            push!(r, Major(BoundsTightened), "bounds on type parameter {} tightened: {:?}", name, tightened);
+        }
+        if !loosened.is_empty() {
+            push!(r, Minor(BoundsLoosened), "bounds on type parameter {} loosened: {:?}", name, loosened);
+        }
+    }
+    for param in new_generics.ty_params.iter().skip(generics.ty_params.len()) {
+        let name = param.ident.name;
+        if !generalized.contains(&name) {
+            push!(r, Breaking(TypeParamAdded), "type parameter {} introduced", name);
+        }
     }
-    // TODO
 }
 
-// TODO: Lints
-// Minor: "introducing new lint warnings/errors"
-// - Any change which will cause downstream code to emit new lints
-// - Can this even be checked for?
+/// Report an argument of type `orig_ty` which has been replaced by the new
+/// type parameter `param`.
+fn compare_generalization(r: &mut Report, cx: &Context, idx: usize, param: Name, orig_ty: &Ty, new_generics: &Generics) {
+    let mut bounds = param_bounds(new_generics, param, &|path| cx.types.resolve_new_here(path));
+    let maybe_sized = bounds.remove("?std::marker::Sized");
+    if bounds.is_empty() {
+        push!(r, Minor(ArgGeneralized), "argument {} generalized to type parameter {}", idx, param);
+    } else if maybe_sized && bounds == trait_object_bounds(orig_ty, &|path| cx.types.resolve_old_here(path)) {
+        // foo(_: &Trait) to foo<T: Trait + ?Sized>(t: &T)
+        push!(r, Minor(ArgGeneralized), "argument {} generalized from trait object to type parameter {}", idx, param);
+    } else {
+        // Unless the old type meets every bound, callers passing it break, and
+        // there is no telling from here whether it does.
+        let r = push!(r, Major(ArgGeneralized), "argument {} generalized to bounded type parameter {}", idx, param);
+        push!(r, Note, "{} may not satisfy the bounds {:?}", pprust::ty_to_string(orig_ty), bounds);
+    }
+}
+
+/// If `new` is a type parameter introduced in `new_generics`, possibly behind
+/// pointers matching those in `old`, return it and the type it replaced.
+fn generalized_param<'a>(old: &'a Ty, old_generics: &Generics, new: &Ty, new_generics: &Generics) -> Option<(Name, &'a Ty)> {
+    match (&old.node, &new.node) {
+        (&TyKind::Rptr(_, ref old_mt), &TyKind::Rptr(_, ref new_mt)) |
+        (&TyKind::Ptr(ref old_mt), &TyKind::Ptr(ref new_mt)) if old_mt.mutbl == new_mt.mutbl => {
+            generalized_param(&old_mt.ty, old_generics, &new_mt.ty, new_generics)
+        }
+        (_, &TyKind::Path(None, ref path)) if !path.global && path.segments.len() == 1 => {
+            let name = path.segments[0].identifier.name;
+            if type_param_index(new_generics, name).map_or(false, |idx| idx >= old_generics.ty_params.len()) {
+                Some((name, old))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The traits named by a trait object type, as printed by `resolved_bounds`.
+fn trait_object_bounds(ty: &Ty, resolve: &Fn(&Path) -> Vec<String>) -> BTreeSet<String> {
+    match ty.node {
+        TyKind::Path(None, ref path) => Some(resolved_trait(path, resolve)).into_iter().collect(),
+        TyKind::ObjectSum(ref ty, ref bounds) => {
+            let mut result = trait_object_bounds(ty, resolve);
+            result.extend(resolved_bounds(bounds, resolve));
+            result
+        }
+        TyKind::PolyTraitRef(ref bounds) => resolved_bounds(bounds, resolve),
+        TyKind::Paren(ref ty) => trait_object_bounds(ty, resolve),
+        _ => BTreeSet::new(),
+    }
+}
+
+pub fn is_type_param(generics: &Generics, name: Name) -> bool {
+    generics.ty_params.iter().any(|p| p.ident.name == name)
+}
+
+/// The position of a type parameter in its generics.
+pub fn type_param_index(generics: &Generics, name: Name) -> Option<usize> {
+    generics.ty_params.iter().position(|p| p.ident.name == name)
+}
+
+/// Whether the printed form of a type mentions a name, such as a type parameter.
+fn mentions(ty: &Ty, name: Name) -> bool {
+    let name = name.as_str();
+    pprust::ty_to_string(ty).split(|c: char| !c.is_alphanumeric() && c != '_').any(|word| word == &*name)
+}
 
-fn use_defines_name(r: &mut Report, vp: &ViewPath, name: Name) -> bool {
-    match vp.node {
-        ViewPath_::ViewPathSimple(ref ident, _) => ident.name == name,
-        ViewPath_::ViewPathGlob(_) => {
-            push!(r, Warning, "Glob imports are not yet handled");
-            false
-        }
-        ViewPath_::ViewPathList(ref path, ref items) => items.iter().any(|item| match item.node {
-            PathListItemKind::Ident { rename: Some(ref ident), .. } |
-            PathListItemKind::Ident { name: ref ident, .. } |
-            PathListItemKind::Mod { rename: Some(ref ident), .. } =>
-                ident.name == name,
-            PathListItemKind::Mod { .. } => {
-                path.segments.last().map(|p| p.identifier.name == name).unwrap_or(false)
+/// Collect the bounds on a type parameter, from both its declaration and the
+/// where clause, with their traits resolved by `resolve`.
+pub fn param_bounds(generics: &Generics, name: Name, resolve: &Fn(&Path) -> Vec<String>) -> BTreeSet<String> {
+    let mut result = BTreeSet::new();
+    for param in generics.ty_params.iter().filter(|p| p.ident.name == name) {
+        result.extend(resolved_bounds(&param.bounds, resolve));
+    }
+    for predicate in &generics.where_clause.predicates {
+        if let WherePredicate::BoundPredicate(ref bound) = *predicate {
+            if let TyKind::Path(None, ref path) = bound.bounded_ty.node {
+                if !path.global && path.segments.len() == 1 && path.segments[0].identifier.name == name {
+                    result.extend(resolved_bounds(&bound.bounds, resolve));
+                }
             }
-        })
+        }
     }
+    result
 }
 
-struct SearchResult {
+// TODO: Lints
+// Minor: "introducing new lint warnings/errors"
+// - Any change which will cause downstream code to emit new lints
+// - Can this even be checked for?
+
+pub struct SearchResult {
     /// The #[cfg] flags on the input item
-    item_cfg: Config,
+    pub item_cfg: Config,
     /// The union of #[cfg] flags on matching items
-    found_cfgs: Config,
+    pub found_cfgs: Config,
     /// Found an item with a matching name
-    found_name: bool,
+    pub found_name: bool,
     /// ... that is also public
-    found_pub: bool,
+    pub found_pub: bool,
     /// ... that was also a matching kind
-    found_kind: bool,
+    pub found_kind: bool,
+}
+
+/// Something which is looked up by name and may carry #[cfg] attributes.
+pub trait Member {
+    fn name(&self) -> Name;
+    fn config(&self, r: &mut Report) -> Config;
+    fn is_public(&self) -> bool { true }
+}
+
+impl Member for Item {
+    fn name(&self) -> Name { self.ident.name }
+    fn config(&self, r: &mut Report) -> Config { Config::new(r, &self.attrs) }
+    fn is_public(&self) -> bool { is_public(self) }
 }
 
-fn search_items<'a, I, F>(r: &mut Report, orig: &Item, iter: I, mut f: F) -> SearchResult where
-    F: FnMut(&mut Report, &'a Item) -> bool,
-    I: IntoIterator<Item=&'a Item>,
+impl Member for TraitItem {
+    fn name(&self) -> Name { self.ident.name }
+    fn config(&self, r: &mut Report) -> Config { Config::new(r, &self.attrs) }
+}
+
+impl Member for Variant {
+    fn name(&self) -> Name { self.node.name.name }
+    fn config(&self, r: &mut Report) -> Config { Config::new(r, &self.node.attrs) }
+}
+
+pub fn search_items<'a, T, I, F>(r: &mut Report, orig: &T, iter: I, mut f: F) -> SearchResult where
+    T: Member + 'a,
+    F: FnMut(&mut Report, &'a T) -> bool,
+    I: IntoIterator<Item=&'a T>,
 {
     let mut result = SearchResult {
-        item_cfg: Config::new(r, &orig.attrs),
+        item_cfg: orig.config(r),
         found_cfgs: Config::False,
         found_name: false,
         found_pub: false,
@@ -323,12 +981,11 @@ fn search_items<'a, I, F>(r: &mut Report, orig: &Item, iter: I, mut f: F) -> Sea
     result.item_cfg.report(r, &Config::True, "");
 
     for item in iter {
-        let item: &Item = &item;
-        if item.ident.name == orig.ident.name {
+        if item.name() == orig.name() {
             result.found_name = true;
-            if is_public(item) {
+            if item.is_public() {
                 result.found_pub = true;
-                let local_cfg = Config::new(r, &item.attrs);
+                let local_cfg = item.config(r);
                 if result.item_cfg.intersects(&local_cfg) {
                     let r = local_cfg.report(r, &result.item_cfg, "Comparing with ");
                     if f(r, item) {
@@ -344,7 +1001,7 @@ fn search_items<'a, I, F>(r: &mut Report, orig: &Item, iter: I, mut f: F) -> Sea
     result
 }
 
-fn is_public(item: &Item) -> bool {
+pub fn is_public(item: &Item) -> bool {
     use syntax::ast::ItemKind::*;
     match item.node {
         // these nodes are always effectively public
@@ -353,3 +1010,152 @@ fn is_public(item: &Item) -> bool {
         _ => item.vis == Visibility::Public
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use kind::ChangeKind::*;
+    use report::Severity::*;
+    use {compare_sources, findings};
+
+    #[test]
+    fn struct_fields() {
+        let r = compare_sources("pub struct S { pub a: u8 }", "pub struct S { pub a: u8, pub b: u8 }");
+        assert_eq!(findings(&r), vec![(PublicFieldAdded, Major)]);
+        let r = compare_sources("pub struct S { pub a: u8, b: u8 }", "pub struct S { pub a: u8, b: u8, pub c: u8 }");
+        assert_eq!(findings(&r), vec![(PublicFieldAdded, Minor)]);
+
+        let r = compare_sources("pub struct S { pub a: u8 }", "pub struct S { pub a: u8, b: u8 }");
+        assert_eq!(findings(&r), vec![(PrivateFieldsChanged, Major)]);
+        let r = compare_sources("pub struct S { a: u8 }", "pub struct S { b: u16 }");
+        assert_eq!(findings(&r), vec![(PrivateFieldsChanged, Minor)]);
+        let r = compare_sources("pub struct S(pub u8, u8);", "pub struct S(pub u8, u8, u8);");
+        assert_eq!(findings(&r), vec![(PrivateFieldsChanged, Major)]);
+
+        let r = compare_sources("pub struct S { pub a: u8, pub b: u8 }", "pub struct S { pub a: u8 }");
+        assert_eq!(findings(&r), vec![(FieldRemoved, Major)]);
+        let r = compare_sources("pub struct S { pub a: u8 }", "pub struct S { a: u8 }");
+        assert_eq!(findings(&r), vec![(MadePrivate, Major)]);
+        let r = compare_sources("pub struct S(pub u8, pub u8);", "pub struct S(pub u8, u8);");
+        assert_eq!(findings(&r), vec![(MadePrivate, Major)]);
+        let r = compare_sources("pub struct S { pub a: u8 }", "pub struct S { pub a: u16 }");
+        assert_eq!(findings(&r), vec![(TypeChanged, Major)]);
+
+        let r = compare_sources("pub struct S(u8);", "pub struct S { a: u8 }");
+        assert_eq!(findings(&r), vec![(StructKindChanged, Breaking)]);
+        let r = compare_sources("pub struct S(pub u8);", "pub struct S { pub a: u8 }");
+        assert_eq!(findings(&r), vec![(StructKindChanged, Major)]);
+    }
+
+    #[test]
+    fn nonexhaustive_enums() {
+        let r = compare_sources(
+            "pub enum E { A, #[doc(hidden)] __Nonexhaustive }",
+            "pub enum E { A, B, #[doc(hidden)] __Nonexhaustive }");
+        assert_eq!(findings(&r), vec![(VariantAdded, Breaking)]);
+
+        // a hidden variant is only the marker if it comes last
+        let r = compare_sources(
+            "pub enum E { A, #[doc(hidden)] B, C }",
+            "pub enum E { A, #[doc(hidden)] B, C, D }");
+        assert_eq!(findings(&r), vec![(VariantAdded, Major)]);
+    }
+
+    #[test]
+    fn trait_definitions() {
+        let r = compare_sources("pub trait T {}", "pub trait T: Clone {}");
+        assert_eq!(findings(&r), vec![(SupertraitAdded, Major)]);
+        let r = compare_sources("pub trait T: Clone {}", "pub trait T {}");
+        assert_eq!(findings(&r), vec![(SupertraitRemoved, Major)]);
+        // supertraits are compared by the traits they resolve to
+        let r = compare_sources("use std::fmt; pub trait T: fmt::Debug {}", "use std::fmt::Debug; pub trait T: Debug {}");
+        assert!(findings(&r).is_empty());
+        let r = compare_sources("pub mod a { pub trait Debug {} } pub trait T: a::Debug {}", "pub trait T: ::std::fmt::Debug {}");
+        assert_eq!(findings(&r), vec![(ItemRemoved, Major), (SupertraitRemoved, Major), (SupertraitAdded, Major)]);
+        let r = compare_sources(
+            "pub trait T { type A: ::std::clone::Clone; }",
+            "pub trait T { type A: Clone; }");
+        assert!(findings(&r).is_empty());
+
+        let r = compare_sources("pub trait T { fn a(&self); }", "pub trait T { fn a(&self); fn b(&self) {} }");
+        assert_eq!(findings(&r), vec![(DefaultedTraitItemAdded, Breaking)]);
+        let r = compare_sources("pub trait T { fn a(&self); }", "pub trait T { fn a(&self); fn b(&self); }");
+        assert_eq!(findings(&r), vec![(TraitItemAdded, Major)]);
+        let r = compare_sources("pub trait T { fn a(&self) {} }", "pub trait T { fn a(&self); }");
+        assert_eq!(findings(&r), vec![(TraitItemDefaultRemoved, Major)]);
+
+        // any change to a method's signature is Major, as implementors break
+        let r = compare_sources("pub trait T { fn a(&self, x: u8); }", "pub trait T { fn a<U>(&self, x: U); }");
+        assert_eq!(findings(&r), vec![(ArgGeneralized, Major)]);
+
+        let r = compare_sources("pub trait T {}", "pub unsafe trait T {}");
+        assert_eq!(findings(&r), vec![(TraitUnsafetyChanged, Major)]);
+    }
+
+    #[test]
+    fn function_signatures() {
+        let r = compare_sources("pub fn f(_: u8) {}", "pub fn f<T>(_: T) {}");
+        assert_eq!(findings(&r), vec![(ArgGeneralized, Minor)]);
+        let r = compare_sources("pub fn f(_: u8) {}", "pub fn f<T: Into<u64>>(_: T) {}");
+        assert_eq!(findings(&r), vec![(ArgGeneralized, Major)]);
+        let r = compare_sources("pub fn f(_: u8) {}", "pub fn f<T: Iterator>(_: T) {}");
+        assert_eq!(findings(&r), vec![(ArgGeneralized, Major)]);
+        let r = compare_sources(
+            "use std::fmt::Display; pub fn f(_: &Display) {}",
+            "use std::fmt::Display; pub fn f<T: Display + ?Sized>(_: &T) {}");
+        assert_eq!(findings(&r), vec![(ArgGeneralized, Minor)]);
+
+        let r = compare_sources("pub fn f() {}", "pub fn f<T>() {}");
+        assert_eq!(findings(&r), vec![(TypeParamAdded, Breaking)]);
+        let r = compare_sources("pub fn f<T>(_: T) {}", "pub fn f<T: Clone>(_: T) {}");
+        assert_eq!(findings(&r), vec![(BoundsTightened, Major)]);
+        // bounds are compared by the traits they resolve to
+        let r = compare_sources("pub fn f<T: ::std::clone::Clone>(_: T) {}", "pub fn f<T: Clone>(_: T) {}");
+        assert!(findings(&r).is_empty());
+        let r = compare_sources(
+            "use std::fmt; pub fn f<T>(_: T) where T: fmt::Display {}",
+            "use std::fmt::Display; pub fn f<T: Display>(_: T) {}");
+        assert!(findings(&r).is_empty());
+        let r = compare_sources(
+            "pub trait Clone {} pub fn f<T: Clone>(_: T) {}",
+            "pub trait Clone {} pub fn f<T: ::std::clone::Clone>(_: T) {}");
+        assert_eq!(findings(&r), vec![(BoundsTightened, Major), (BoundsLoosened, Minor)]);
+        let r = compare_sources(
+            "use std::fmt; pub fn f(_: &fmt::Display) {}",
+            "use std::fmt::Display; pub fn f<T: Display + ?Sized>(_: &T) {}");
+        assert_eq!(findings(&r), vec![(ArgGeneralized, Minor)]);
+
+        let r = compare_sources("pub fn f(_: u8) {}", "pub fn f(_: u8, _: u8) {}");
+        assert_eq!(findings(&r), vec![(ArgCountChanged, Major)]);
+        let r = compare_sources("pub fn f(_: u8) {}", "pub fn f(_: u16) {}");
+        assert_eq!(findings(&r), vec![(ArgTypeChanged, Major)]);
+        let r = compare_sources("pub fn f() {}", "pub fn f() -> u8 { 0 }");
+        assert_eq!(findings(&r), vec![(ReturnTypeChanged, Major)]);
+        let r = compare_sources("pub fn f() {}", "pub unsafe fn f() {}");
+        assert_eq!(findings(&r), vec![(FnUnsafetyChanged, Major)]);
+    }
+
+    #[test]
+    fn type_definitions() {
+        let r = compare_sources("pub struct S(pub u8);", "pub struct S<T = u8>(pub T);");
+        assert_eq!(findings(&r), vec![(DefaultedTypeParamAdded, Minor), (TypeGeneralized, Minor)]);
+        let r = compare_sources("pub struct S<T>(pub T);", "pub struct S<T: Clone>(pub T);");
+        assert_eq!(findings(&r), vec![(BoundsTightened, Major)]);
+        let r = compare_sources("pub struct S<T: ::std::clone::Clone>(pub T);", "pub struct S<T: Clone>(pub T);");
+        assert!(findings(&r).is_empty());
+        let r = compare_sources("pub struct S<T = u8>(pub T);", "pub struct S<T = u16>(pub T);");
+        assert_eq!(findings(&r), vec![(TypeParamDefaultChanged, Major)]);
+        let r = compare_sources("pub struct S<'a>(pub &'a u8);", "pub struct S<'a, 'b>(pub &'a u8, pub &'b u8);");
+        assert!(findings(&r).contains(&(LifetimeParamsChanged, Major)));
+
+        let r = compare_sources("pub type A = u8;", "pub type A = u16;");
+        assert_eq!(findings(&r), vec![(TypeChanged, Major)]);
+        let r = compare_sources("pub type A = Vec<u8>;", "pub type A<T = u8> = Vec<T>;");
+        assert_eq!(findings(&r), vec![(DefaultedTypeParamAdded, Minor), (TypeGeneralized, Minor)]);
+
+        // aliases are expanded before types are compared
+        let r = compare_sources("pub type A = u8; pub fn f(_: A) {}", "pub type A = u8; pub fn f(_: u8) {}");
+        assert!(findings(&r).is_empty());
+        let r = compare_sources("pub type A = u8; pub fn f(_: A) {}", "pub type A = u16; pub fn f(_: A) {}");
+        assert_eq!(findings(&r), vec![(TypeChanged, Major), (ArgTypeChanged, Major)]);
+    }
+}
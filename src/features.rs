@@ -0,0 +1,190 @@
+//! Cargo features
+//!
+//! Features are not independent of each other: enabling one enables
+//! everything it lists in `[features]`, and `default` is enabled unless
+//! opted out of. `#[cfg(feature = "a")]` therefore applies whenever any
+//! feature which implies `a` is requested.
+//!
+//! Each crate's `cfg` attributes are rewritten with its own feature graph so
+//! that feature atoms in a `Config` stand for the features a user *requests*,
+//! which are independent. For example, with `full = ["a"]`,
+//! `#[cfg(feature = "a")]` becomes `#[cfg(any(feature = "a", feature = "full"))]`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use syntax::ast::*;
+use syntax::attr::{self, AttrMetaMethods};
+use syntax::codemap::Spanned;
+use syntax::fold::{self, Folder};
+use syntax::parse::token;
+use syntax::ptr::P;
+use toml;
+
+use manifest::Manifest;
+use report::*;
+
+/// The features of a package, from its Cargo.toml.
+#[derive(Clone, Debug, Default)]
+pub struct Features {
+    /// Each feature and the features it directly enables
+    graph: BTreeMap<String, Vec<String>>,
+}
+
+impl Features {
+    /// Read the feature graph from a manifest.
+    ///
+    /// Optional dependencies count as features of the same name, and
+    /// `dep/feature` entries enable the dependency.
+    pub fn from_manifest(manifest: &Manifest) -> Features {
+        let mut graph = BTreeMap::new();
+        if let Some(deps) = manifest.lookup("dependencies").and_then(toml::Value::as_table) {
+            for (name, dep) in deps {
+                if dep.lookup("optional").and_then(toml::Value::as_bool) == Some(true) {
+                    graph.insert(name.clone(), Vec::new());
+                }
+            }
+        }
+        if let Some(features) = manifest.lookup("features").and_then(toml::Value::as_table) {
+            for (name, enables) in features {
+                let enables = enables.as_slice().map_or(Vec::new(), |list| list.iter()
+                    .filter_map(toml::Value::as_str)
+                    .map(|entry| entry.split('/').next().unwrap().to_owned())
+                    .collect());
+                graph.insert(name.clone(), enables);
+            }
+        }
+        Features { graph: graph }
+    }
+
+    /// Whether the named feature exists.
+    pub fn contains(&self, feature: &str) -> bool {
+        self.graph.contains_key(feature)
+    }
+
+    /// The names of every feature.
+    pub fn names(&self) -> Vec<&str> {
+        self.graph.keys().map(|name| &**name).collect()
+    }
+
+    /// Every feature enabled by requesting the given one, including itself.
+    pub fn enabled_by(&self, feature: &str) -> BTreeSet<String> {
+        let mut enabled = BTreeSet::new();
+        let mut stack = vec![feature];
+        while let Some(current) = stack.pop() {
+            if self.graph.contains_key(current) && enabled.insert(current.to_owned()) {
+                stack.extend(self.graph[current].iter().map(|s| &**s));
+            }
+        }
+        enabled
+    }
+
+    /// Every feature which enables the given one when requested, with the
+    /// feature itself first.
+    pub fn implied_by(&self, feature: &str) -> Vec<String> {
+        let mut result = vec![feature.to_owned()];
+        result.extend(self.graph.keys()
+            .filter(|&other| other != feature && self.enabled_by(other).contains(feature))
+            .cloned());
+        result
+    }
+
+    /// Rewrite the `cfg` attributes of a crate in terms of requested
+    /// features.
+    pub fn apply(&self, krate: Crate) -> Crate {
+        FeatureFolder { features: self }.fold_crate(krate)
+    }
+}
+
+/// Report features which were removed or added, and changes to the set of
+/// features enabled by default.
+pub fn compare_features(r: &mut Report, old: &Features, new: &Features) {
+    for name in old.graph.keys().filter(|name| !new.contains(name)) {
+        push!(r, Major(FeatureRemoved), "feature {} removed", name).with_path(feature_path(name));
+    }
+    for name in new.graph.keys().filter(|name| !old.contains(name)) {
+        push!(r, Minor(FeatureAdded), "feature {} added", name).with_path(feature_path(name));
+    }
+
+    let old_default = old.enabled_by("default");
+    let new_default = new.enabled_by("default");
+    for name in old_default.difference(&new_default).filter(|name| *name != "default" && new.contains(name)) {
+        push!(r, Major(DefaultFeatureRemoved), "feature {} no longer enabled by default", name)
+            .with_path(feature_path(name));
+    }
+    for name in new_default.difference(&old_default).filter(|name| *name != "default" && old.contains(name)) {
+        push!(r, Minor(DefaultFeatureAdded), "feature {} now enabled by default", name)
+            .with_path(feature_path(name));
+    }
+}
+
+/// The path used in reports for a feature, matching its key in Cargo.toml.
+fn feature_path(name: &str) -> String {
+    format!("features.{}", name)
+}
+
+struct FeatureFolder<'a> {
+    features: &'a Features,
+}
+
+impl<'a> FeatureFolder<'a> {
+    fn rewrite(&self, meta: P<MetaItem>) -> P<MetaItem> {
+        meta.map(|Spanned { node, span }| {
+            let node = match node {
+                MetaItemKind::List(name, items) =>
+                    MetaItemKind::List(name, items.into_iter().map(|item| self.rewrite(item)).collect()),
+                MetaItemKind::NameValue(name, lit) => {
+                    let implied = match lit.node {
+                        LitKind::Str(ref feature, _) if &*name == "feature" => self.features.implied_by(feature),
+                        _ => Vec::new(),
+                    };
+                    if implied.len() > 1 {
+                        MetaItemKind::List(token::intern_and_get_ident("any"), implied.iter()
+                            .map(|feature| attr::mk_name_value_item_str(name.clone(), token::intern_and_get_ident(feature)))
+                            .collect())
+                    } else {
+                        MetaItemKind::NameValue(name, lit)
+                    }
+                }
+                node => node,
+            };
+            Spanned { node: node, span: span }
+        })
+    }
+}
+
+impl<'a> Folder for FeatureFolder<'a> {
+    fn fold_attribute(&mut self, attr: Attribute) -> Option<Attribute> {
+        if !attr.check_name("cfg") {
+            return Some(attr)
+        }
+        let Spanned { node: Attribute_ { id, style, value, is_sugared_doc }, span } = attr;
+        Some(Spanned {
+            node: Attribute_ { id: id, style: style, value: self.rewrite(value), is_sugared_doc: is_sugared_doc },
+            span: span,
+        })
+    }
+
+    fn fold_mac(&mut self, mac: Mac) -> Mac {
+        fold::noop_fold_mac(mac, self)
+    }
+}
+
+#[test]
+fn implications() {
+    use std::path::Path;
+
+    let manifest = Manifest::parse(Path::new("Cargo.toml"), r#"
+        [dependencies]
+        serde = { version = "0.8", optional = true }
+        [features]
+        default = ["std"]
+        std = []
+        full = ["std", "extra"]
+        extra = ["serde/std"]
+    "#).unwrap();
+    let features = Features::from_manifest(&manifest);
+    assert_eq!(features.implied_by("std"), vec!["std", "default", "full"]);
+    assert_eq!(features.implied_by("serde"), vec!["serde", "extra", "full"]);
+    assert_eq!(features.implied_by("full"), vec!["full"]);
+    assert!(features.enabled_by("full").contains("serde"));
+}
@@ -0,0 +1,681 @@
+//! trait and inherent implementations
+//!
+//! Implementations have no path of their own, so rather than being compared
+//! module by module like other items, they are collected from the entire
+//! crate and matched up by what they implement.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use syntax::ast::*;
+use syntax::codemap::Span;
+use syntax::attr::AttrMetaMethods;
+use syntax::print::pprust;
+use syntax::ptr::P;
+
+use report::*;
+use cfg::Config;
+use kind::ChangeKind;
+use compare::{Context, Member, search_items, compare_functions, param_bounds};
+use resolve::{Module, Namespaces};
+use types::Relation;
+
+/// Traits which are `#[fundamental]`: implementing them is a Major change.
+const FUNDAMENTAL_TRAITS: &'static [&'static str] = &["Sized", "Fn", "FnMut", "FnOnce"];
+/// Types which are `#[fundamental]`: implementing traits for them is Major.
+const FUNDAMENTAL_TYPES: &'static [&'static str] = &["Box"];
+
+/// A trait implementation, identified by its pretty-printed trait, self type,
+/// and generics. Impls in the same crate are grouped by this, but matched to
+/// those in the other crate by resolving and comparing their types.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct TraitImplKey {
+    trait_name: String,
+    self_ty: String,
+    generics: String,
+}
+
+struct TraitImpl<'a> {
+    /// The trait implemented
+    trait_path: &'a Path,
+    /// The self type and the impl's generics, or `None` for `impl Trait for ..`
+    self_ty: Option<(&'a Ty, &'a Generics)>,
+    /// The path of the module containing the first matching impl
+    module: Vec<Name>,
+    /// The path findings about this impl are reported at: the resolved
+    /// paths of its self type and trait
+    path: String,
+    /// The union of #[cfg] flags on all matching impls, including those on
+    /// their parent modules
+    config: Config,
+    /// Whether this is a `!Trait` impl
+    negative: bool,
+    /// Whether this impl was produced by #[derive]
+    derived: bool,
+    /// Whether implementing this trait is Major
+    fundamental: bool,
+    /// Where the first matching impl is
+    span: Span,
+}
+
+/// A public item in an inherent impl.
+struct InherentItem<'a> {
+    item: &'a ImplItem,
+    /// The type the impl is for
+    self_ty: &'a Ty,
+    /// The impl's generics, which are in scope in the item
+    generics: &'a Generics,
+    /// The path of the module containing the impl
+    module: Vec<Name>,
+    /// The resolved path of the self type, followed by the item's name
+    path: String,
+    /// The #[cfg] flags on the item, its impl block, and its self type
+    config: Config,
+}
+
+impl<'a> Member for InherentItem<'a> {
+    fn name(&self) -> Name { self.item.ident.name }
+    fn config(&self, _: &mut Report) -> Config { self.config.clone() }
+    fn is_public(&self) -> bool { self.item.vis == Visibility::Public }
+}
+
+/// Types and traits defined in the crate, by the path they are defined at,
+/// for determining whether an impl is visible to downstream crates, and how
+/// paths in the crate resolve.
+struct LocalNames<'r> {
+    all: HashSet<Vec<String>>,
+    /// Those which downstream crates can name, through any path
+    public: HashSet<Vec<String>>,
+    /// The union of #[cfg] flags on the types defined at each path
+    configs: HashMap<Vec<String>, Config>,
+    /// Resolves a path used in a module to a path from the crate root
+    resolve: &'r Fn(&[Name], &Path) -> Vec<String>,
+}
+
+impl<'r> LocalNames<'r> {
+    fn new(r: &mut Report, namespaces: &Namespaces, resolve: &'r Fn(&[Name], &Path) -> Vec<String>) -> LocalNames<'r> {
+        let mut names = LocalNames {
+            all: HashSet::new(),
+            public: HashSet::new(),
+            configs: HashMap::new(),
+            resolve: resolve,
+        };
+        let root = namespaces.root();
+        names.walk(r, root.module, &[], &Config::True);
+        names.walk_public(namespaces, &root, &mut HashSet::new());
+        for config in names.configs.values_mut() {
+            config.simplify();
+        }
+        names
+    }
+
+    fn walk(&mut self, r: &mut Report, module: &Mod, path: &[String], module_cfg: &Config) {
+        use syntax::ast::ItemKind::*;
+        for item in &module.items {
+            let mut config = Config::All(vec![module_cfg.clone(), Config::new(r, &item.attrs)]);
+            config.simplify();
+            let mut item_path = path.to_vec();
+            item_path.push(item.ident.name.to_string());
+            match item.node {
+                Struct(..) | Enum(..) | Ty(..) | Trait(..) => {
+                    self.all.insert(item_path.clone());
+                    self.configs.entry(item_path).or_insert(Config::False).union(config);
+                }
+                Mod(ref inner) => self.walk(r, inner, &item_path, &config),
+                _ => {}
+            }
+        }
+    }
+
+    /// Find the types and traits visible from the crate root, including
+    /// those re-exported from private modules.
+    fn walk_public<'a>(&mut self, namespaces: &Namespaces<'a>, module: &Module<'a>, seen: &mut HashSet<*const Mod>) {
+        use syntax::ast::ItemKind::*;
+        if !seen.insert(module.module) { return }
+        for entry in namespaces.entries(module) {
+            if entry.external.is_some() || !entry.is_public() { continue }
+            match entry.item.node {
+                Struct(..) | Enum(..) | Ty(..) | Trait(..) => {
+                    self.public.insert(entry.child_path().iter().map(|name| name.to_string()).collect());
+                }
+                Mod(ref inner) => {
+                    let child = Module { path: entry.child_path(), module: inner, via: Vec::new() };
+                    self.walk_public(namespaces, &child, seen);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The #[cfg] flags on the type an impl in `module` is for, if it is
+    /// local.
+    fn config(&self, module: &[Name], ty: &Ty) -> Config {
+        base_path(ty).and_then(|path| self.configs.get(&(self.resolve)(module, path))).cloned().unwrap_or(Config::True)
+    }
+
+    /// Whether a path used in `module` names a type or trait which
+    /// downstream crates cannot see.
+    fn is_hidden(&self, module: &[Name], path: &Path) -> bool {
+        let path = (self.resolve)(module, path);
+        self.all.contains(&path) && !self.public.contains(&path)
+    }
+
+    /// The path of the type an impl in `module` is for, from the crate root,
+    /// or the type as written if it is not named by a path.
+    fn self_ty_path(&self, module: &[Name], ty: &Ty) -> String {
+        match base_path(ty) {
+            Some(path) => (self.resolve)(module, path).join("::"),
+            None => pprust::ty_to_string(ty),
+        }
+    }
+}
+
+// Trait implementations
+// Major: "implementing any "fundamental" trait."
+// Breaking: "implementing any non-fundamental trait."
+// Removing an implementation is not covered by the RFC, but is always Major.
+pub fn compare_impls(r: &mut Report, cx: &Context, old: &Crate, new: &Crate) {
+    let resolve_old = |module: &[Name], path: &Path| cx.types.resolve_old(module, path);
+    let resolve_new = |module: &[Name], path: &Path| cx.types.resolve_new(module, path);
+    let old_names = LocalNames::new(r, &cx.old_names, &resolve_old);
+    let new_names = LocalNames::new(r, &cx.new_names, &resolve_new);
+    compare_trait_impls(r, cx, (&old_names, &old.module), (&new_names, &new.module));
+    compare_inherent_impls(r, cx, (&old_names, &old.module), (&new_names, &new.module));
+}
+
+fn compare_trait_impls(r: &mut Report, cx: &Context, (old_names, old): (&LocalNames, &Mod), (new_names, new): (&LocalNames, &Mod)) {
+    let r = push!(r, Lazy Note, "trait impls");
+    let old_impls = collect_trait_impls(r, old_names, old);
+    let new_impls = collect_trait_impls(r, new_names, new);
+
+    for (key, old_impl) in &old_impls {
+        let found = find_matching(new_impls.values().filter(|new_impl| same_impl(cx, old_impl, new_impl)));
+        let description = describe(key, old_impl);
+        let new_span = found.as_ref().map(|&(_, span)| span);
+        let r = push!(r, Lazy Note, description)
+            .with_path(old_impl.path.clone())
+            .with_locations(cx.old.locate(old_impl.span), new_span.and_then(|s| cx.new.locate(s)));
+        match found {
+            None => { push!(r, Major(ImplRemoved)); }
+            Some((new_config, _)) => if !old_impl.config.subset(&new_config) {
+                push!(r, Major(CfgNarrowed), "availability narrowed:\n  Was: {}\n  Now: {}", old_impl.config, new_config);
+            },
+        }
+    }
+
+    for (key, new_impl) in &new_impls {
+        let found = find_matching(old_impls.values().filter(|old_impl| same_impl(cx, old_impl, new_impl)));
+        let (kind, what) = match found {
+            None if new_impl.negative => (ChangeKind::NegativeImplAdded, "added".to_owned()),
+            None if new_impl.fundamental => (ChangeKind::FundamentalImplAdded, "added".to_owned()),
+            None => (ChangeKind::ImplAdded, "added".to_owned()),
+            Some((ref old_config, _)) => if !new_impl.config.subset(old_config) {
+                (ChangeKind::CfgWidened, format!("availability widened:\n  Was: {}\n  Now: {}", old_config, new_impl.config))
+            } else {
+                continue
+            },
+        };
+        let description = describe(key, new_impl);
+        let old_span = found.as_ref().map(|&(_, span)| span);
+        let r = push!(r, Lazy Note, description)
+            .with_path(new_impl.path.clone())
+            .with_locations(old_span.and_then(|s| cx.old.locate(s)), cx.new.locate(new_impl.span));
+        if new_impl.negative {
+            push!(r, Major[kind], "{} (negative impl)", what);
+        } else if new_impl.fundamental {
+            push!(r, Major[kind], "{} (fundamental)", what);
+        } else {
+            push!(r, Breaking[kind], what);
+        }
+    }
+}
+
+/// Whether an old and a new trait impl are of the same trait for the same
+/// type. Paths, including those of the traits bounding type parameters, are
+/// resolved in the impls' modules, and type parameters are matched by
+/// position.
+fn same_impl(cx: &Context, old: &TraitImpl, new: &TraitImpl) -> bool {
+    if old.negative != new.negative {
+        return false
+    }
+    cx.types.set_modules(&old.module, &new.module);
+    match (old.self_ty, new.self_ty) {
+        (Some((old_ty, old_generics)), Some((new_ty, new_generics))) => {
+            let equal = |o: &Ty, n: &Ty| cx.types.compare_generic((o, old_generics), (n, new_generics)) == Relation::Equal;
+            let same_bounds = old_generics.ty_params.len() == new_generics.ty_params.len() &&
+                old_generics.ty_params.iter().zip(new_generics.ty_params.iter()).all(|(o, n)| {
+                    param_bounds(old_generics, o.ident.name, &|path| cx.types.resolve_old_here(path)) ==
+                        param_bounds(new_generics, n.ident.name, &|path| cx.types.resolve_new_here(path))
+                });
+            same_bounds && equal(&trait_ty(old.trait_path), &trait_ty(new.trait_path)) && equal(old_ty, new_ty)
+        }
+        (None, None) => cx.types.compare(&trait_ty(old.trait_path), &trait_ty(new.trait_path)) == Relation::Equal,
+        _ => false,
+    }
+}
+
+/// The union of the #[cfg] flags on the given impls, and where the first one
+/// is, or `None` if there are none.
+fn find_matching<'a, 'b: 'a, I: Iterator<Item=&'a TraitImpl<'b>>>(impls: I) -> Option<(Config, Span)> {
+    impls.fold(None, |found: Option<(Config, Span)>, imp| match found {
+        Some((mut config, span)) => {
+            config.union(imp.config.clone());
+            Some((config, span))
+        }
+        None => Some((imp.config.clone(), imp.span)),
+    }).map(|(mut config, span)| {
+        config.simplify();
+        (config, span)
+    })
+}
+
+/// A trait's path as a type, so that it can be resolved and compared like one.
+fn trait_ty(path: &Path) -> Ty {
+    Ty { id: DUMMY_NODE_ID, node: TyKind::Path(None, path.clone()), span: path.span }
+}
+
+fn describe(key: &TraitImplKey, imp: &TraitImpl) -> String {
+    let trait_name = imp.trait_path.segments.last().map_or(String::new(), |s| s.identifier.name.to_string());
+    format!("impl{} {}{} for {}{}",
+        key.generics,
+        if imp.negative { "!" } else { "" },
+        trait_name,
+        key.self_ty,
+        if imp.derived { " (derived)" } else { "" })
+}
+
+fn collect_trait_impls<'a>(r: &mut Report, names: &LocalNames, krate: &'a Mod) -> BTreeMap<TraitImplKey, TraitImpl<'a>> {
+    let mut impls = BTreeMap::new();
+    walk_trait_impls(r, names, krate, &[], &Config::True, &mut impls);
+    for imp in impls.values_mut() {
+        imp.config.simplify();
+    }
+    impls
+}
+
+fn walk_trait_impls<'a>(r: &mut Report,
+    names: &LocalNames,
+    module: &'a Mod,
+    path: &[Name],
+    module_cfg: &Config,
+    out: &mut BTreeMap<TraitImplKey, TraitImpl<'a>>)
+{
+    use syntax::ast::ItemKind::*;
+    for item in &module.items {
+        let mut config = Config::All(vec![module_cfg.clone(), Config::new(r, &item.attrs)]);
+        config.simplify();
+
+        let (key, trait_path, self_ty, negative, fundamental, impl_path) = match item.node {
+            Mod(ref inner) => {
+                let mut inner_path = path.to_vec();
+                inner_path.push(item.ident.name);
+                walk_trait_impls(r, names, inner, &inner_path, &config, out);
+                continue
+            }
+            Impl(_, polarity, ref generics, Some(ref trait_ref), ref self_ty, _) => {
+                if base_path(self_ty).map_or(false, |p| names.is_hidden(path, p)) || names.is_hidden(path, &trait_ref.path) {
+                    continue
+                }
+                let fundamental = is_fundamental_trait(&trait_ref.path) ||
+                    base_name(self_ty).map_or(false, |n| FUNDAMENTAL_TYPES.contains(&&*n.as_str()));
+                (TraitImplKey {
+                    trait_name: pprust::path_to_string(&trait_ref.path),
+                    self_ty: pprust::ty_to_string(self_ty),
+                    generics: generics_to_string(generics),
+                }, &trait_ref.path, Some((&**self_ty, generics)), polarity == ImplPolarity::Negative, fundamental,
+                format!("{} as {}", names.self_ty_path(path, self_ty), (names.resolve)(path, &trait_ref.path).join("::")))
+            }
+            DefaultImpl(_, ref trait_ref) => {
+                (TraitImplKey {
+                    trait_name: pprust::path_to_string(&trait_ref.path),
+                    self_ty: "..".to_owned(),
+                    generics: String::new(),
+                }, &trait_ref.path, None, false, is_fundamental_trait(&trait_ref.path),
+                (names.resolve)(path, &trait_ref.path).join("::"))
+            }
+            _ => continue
+        };
+
+        let derived = item.attrs.iter().any(|a| &*a.name() == "automatically_derived");
+        let entry = out.entry(key).or_insert_with(|| TraitImpl {
+            trait_path: trait_path,
+            self_ty: self_ty,
+            module: path.to_vec(),
+            path: impl_path,
+            config: Config::False,
+            negative: negative,
+            derived: derived,
+            fundamental: fundamental,
+            span: item.span,
+        });
+        entry.config.union(config);
+    }
+}
+
+fn is_fundamental_trait(path: &Path) -> bool {
+    path.segments.last().map_or(false, |s| FUNDAMENTAL_TRAITS.contains(&&*s.identifier.name.as_str()))
+}
+
+// Inherent implementations
+// Breaking: "adding any inherent items."
+// Items are matched by their self type and name, regardless of which impl
+// block they appear in.
+fn compare_inherent_impls(r: &mut Report, cx: &Context, (old_names, old): (&LocalNames, &Mod), (new_names, new): (&LocalNames, &Mod)) {
+    let r = push!(r, Lazy Note, "inherent impls");
+    let old_items = collect_inherent_items(r, old_names, old);
+    let new_items = collect_inherent_items(r, new_names, new);
+    let no_items = Vec::new();
+
+    for (name, items) in &old_items {
+        let named = new_items.get(name).unwrap_or(&no_items);
+        for item in items {
+            if !item.is_public() { continue }
+            let candidates: Vec<_> = named.iter().filter(|new_item| same_self_ty(cx, item, new_item)).collect();
+            let self_ty = pprust::ty_to_string(item.self_ty);
+            let new_span = candidates.first().map(|c| c.item.span);
+            let r = push!(r, Note, "{} {}::{}", impl_item_kind(item.item), self_ty, name)
+                .with_path(item.path.clone())
+                .with_locations(cx.old.locate(item.item.span), new_span.and_then(|s| cx.new.locate(s)));
+            let result = search_items(r, item, candidates, |r, new_item| {
+                cx.types.set_modules(&item.module, &new_item.module);
+                compare_impl_items(r, cx, item, new_item)
+            });
+            if !result.found_name {
+                push!(r, Major(ItemRemoved));
+            } else if !result.found_pub {
+                push!(r, Major(MadePrivate));
+            } else if !result.found_kind {
+                push!(r, Major(ItemKindChanged));
+            } else if !result.item_cfg.subset(&result.found_cfgs) {
+                push!(r, Major(CfgNarrowed), "availability narrowed:\n  Was: {}\n  Now: {}", result.item_cfg, result.found_cfgs);
+            }
+        }
+    }
+
+    for (name, items) in &new_items {
+        let named = old_items.get(name).unwrap_or(&no_items);
+        for item in items {
+            if !item.is_public() { continue }
+            let candidates: Vec<_> = named.iter().filter(|old_item| same_self_ty(cx, old_item, item)).collect();
+            let self_ty = pprust::ty_to_string(item.self_ty);
+            let old_span = candidates.first().map(|c| c.item.span);
+            let r = push!(r, Lazy Note, "{} {}::{}", impl_item_kind(item.item), self_ty, name)
+                .with_path(item.path.clone())
+                .with_locations(old_span.and_then(|s| cx.old.locate(s)), cx.new.locate(item.item.span));
+            let result = search_items(r, item, candidates, |_, old_item| {
+                impl_item_kind(old_item.item) == impl_item_kind(item.item)
+            });
+            if !result.found_name {
+                push!(r, Breaking(ItemAdded));
+            } else if !result.found_pub {
+                push!(r, Breaking(MadePublic));
+            } else if !result.found_kind {
+                // already reported as a change of kind on the old side
+            } else if !result.item_cfg.subset(&result.found_cfgs) {
+                push!(r, Breaking(CfgWidened), "availability widened:\n  Was: {}\n  Now: {}", result.found_cfgs, result.item_cfg);
+            }
+        }
+    }
+}
+
+/// Whether two inherent items are implemented for the same type, with the
+/// impls' type parameters matched by position.
+fn same_self_ty(cx: &Context, old: &InherentItem, new: &InherentItem) -> bool {
+    cx.types.set_modules(&old.module, &new.module);
+    cx.types.compare_generic((old.self_ty, old.generics), (new.self_ty, new.generics)) == Relation::Equal
+}
+
+/// Compare two inherent items of the same name, returning whether they are of
+/// the same kind.
+fn compare_impl_items(r: &mut Report, cx: &Context, old: &InherentItem, new: &InherentItem) -> bool {
+    use syntax::ast::ImplItemKind::*;
+    let (old_generics, new_generics) = (old.generics, new.generics);
+    let (old, new) = (old.item, new.item);
+    match (&old.node, &new.node) {
+        (&Const(ref ty, _), &Const(ref new_ty, _)) => {
+            if cx.types.compare_generic((ty, old_generics), (new_ty, new_generics)) != Relation::Equal {
+                changed!(r, Major(TypeChanged), "const {}'s type", (ty => new_ty), old.ident);
+            }
+        }
+        (&Method(ref sig, _), &Method(ref new_sig, _)) => {
+            if sig.constness == Constness::Const && new_sig.constness == Constness::NotConst {
+                push!(r, Major(ConstQualifierRemoved));
+            }
+            compare_functions(r, cx,
+                (&sig.decl, &item_generics(old_generics, &sig.generics), sig.unsafety, sig.abi),
+                (&new_sig.decl, &item_generics(new_generics, &new_sig.generics), new_sig.unsafety, new_sig.abi));
+        }
+        (&Type(ref ty), &Type(ref new_ty)) => {
+            if cx.types.compare_generic((ty, old_generics), (new_ty, new_generics)) != Relation::Equal {
+                changed!(r, Major(TypeChanged), "type {}", (ty => new_ty), old.ident);
+            }
+        }
+        (&Macro(_), &Macro(_)) => {}
+        _ => return false
+    }
+    true
+}
+
+fn impl_item_kind(item: &ImplItem) -> &'static str {
+    match item.node {
+        ImplItemKind::Const(..) => "const",
+        ImplItemKind::Method(..) => "fn",
+        ImplItemKind::Type(..) => "type",
+        ImplItemKind::Macro(..) => "macro",
+    }
+}
+
+/// The generics in scope in a method: those of its impl, followed by its own,
+/// so that both are matched by position.
+fn item_generics(impl_generics: &Generics, generics: &Generics) -> Generics {
+    let mut result = impl_generics.clone();
+    result.lifetimes.extend(generics.lifetimes.iter().cloned());
+    result.ty_params = P::from_vec(impl_generics.ty_params.iter().chain(generics.ty_params.iter()).cloned().collect());
+    result.where_clause.predicates.extend(generics.where_clause.predicates.iter().cloned());
+    result
+}
+
+/// Collect the items of inherent impls, keyed by item name.
+fn collect_inherent_items<'a>(r: &mut Report, names: &LocalNames, krate: &'a Mod)
+    -> BTreeMap<String, Vec<InherentItem<'a>>>
+{
+    let mut items = BTreeMap::new();
+    walk_inherent_impls(r, names, krate, &[], &Config::True, &mut items);
+    items
+}
+
+fn walk_inherent_impls<'a>(r: &mut Report,
+    names: &LocalNames,
+    module: &'a Mod,
+    path: &[Name],
+    module_cfg: &Config,
+    out: &mut BTreeMap<String, Vec<InherentItem<'a>>>)
+{
+    use syntax::ast::ItemKind::*;
+    for item in &module.items {
+        let mut config = Config::All(vec![module_cfg.clone(), Config::new(r, &item.attrs)]);
+        config.simplify();
+
+        match item.node {
+            Mod(ref inner) => {
+                let mut inner_path = path.to_vec();
+                inner_path.push(item.ident.name);
+                walk_inherent_impls(r, names, inner, &inner_path, &config, out);
+            }
+            Impl(_, _, ref generics, None, ref self_ty, ref impl_items) => {
+                if base_path(self_ty).map_or(false, |p| names.is_hidden(path, p)) { continue }
+                for impl_item in impl_items {
+                    let mut config = Config::All(vec![
+                        names.config(path, self_ty),
+                        config.clone(),
+                        Config::new(r, &impl_item.attrs),
+                    ]);
+                    config.simplify();
+                    out.entry(impl_item.ident.name.to_string())
+                        .or_insert_with(Vec::new)
+                        .push(InherentItem {
+                            item: impl_item,
+                            self_ty: self_ty,
+                            generics: generics,
+                            module: path.to_vec(),
+                            path: format!("{}::{}", names.self_ty_path(path, self_ty), impl_item.ident.name),
+                            config: config,
+                        });
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn generics_to_string(generics: &Generics) -> String {
+    let mut result = pprust::generics_to_string(generics);
+    result.push_str(&pprust::to_string(|s| s.print_where_clause(&generics.where_clause)));
+    result
+}
+
+/// Find the path of the type an impl is for, looking through references.
+fn base_path(ty: &Ty) -> Option<&Path> {
+    match ty.node {
+        TyKind::Path(_, ref path) => Some(path),
+        TyKind::Rptr(_, ref mut_ty) | TyKind::Ptr(ref mut_ty) => base_path(&mut_ty.ty),
+        TyKind::Paren(ref inner) => base_path(inner),
+        _ => None,
+    }
+}
+
+/// Find the name of the type an impl is for, looking through references.
+fn base_name(ty: &Ty) -> Option<Name> {
+    base_path(ty).and_then(|path| path.segments.last()).map(|s| s.identifier.name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use kind::ChangeKind::*;
+    use report::Report;
+    use report::Severity::*;
+    use suppress::{FILE_NAME, Suppressions};
+    use {compare_sources, findings};
+
+    fn suppress(r: &mut Report, path: &str, kind: &str) {
+        let text = format!("[[suppress]]\npath = \"{}\"\nkind = \"{}\"\nreason = \"intended\"\n", path, kind);
+        Suppressions::parse(Path::new(FILE_NAME), &text).unwrap().apply(r);
+    }
+
+    #[test]
+    fn trait_impls() {
+        let r = compare_sources("pub struct S;", "#[derive(Clone)] pub struct S;");
+        assert_eq!(findings(&r), vec![(ImplAdded, Breaking)]);
+        let r = compare_sources("#[derive(Clone)] pub struct S;", "pub struct S;");
+        assert_eq!(findings(&r), vec![(ImplRemoved, Major)]);
+
+        let r = compare_sources("pub trait T {}", "pub trait T {} impl<U> T for Box<U> {}");
+        assert_eq!(findings(&r), vec![(FundamentalImplAdded, Major)]);
+        let r = compare_sources("pub trait T {} impl<U> T for Box<U> {}", "pub trait T {}");
+        assert_eq!(findings(&r), vec![(ImplRemoved, Major)]);
+        let r = compare_sources("pub struct S;", "pub struct S; impl !Send for S {}");
+        assert_eq!(findings(&r), vec![(NegativeImplAdded, Major)]);
+
+        let r = compare_sources(
+            "pub struct S; impl Default for S { fn default() -> S { S } }",
+            "pub struct S; #[cfg(unix)] impl Default for S { fn default() -> S { S } }");
+        assert_eq!(findings(&r), vec![(CfgNarrowed, Major)]);
+
+        // traits and types are compared as resolved, not as written
+        let r = compare_sources(
+            "use std::fmt; pub struct S; impl fmt::Write for S { fn write_str(&mut self, _: &str) -> fmt::Result { Ok(()) } }",
+            "use std::io; pub struct S; impl io::Write for S { fn write(&mut self, _: &[u8]) -> io::Result<usize> { Ok(0) } fn flush(&mut self) -> io::Result<()> { Ok(()) } }");
+        assert_eq!(findings(&r), vec![(ImplRemoved, Major), (ImplAdded, Breaking)]);
+        let r = compare_sources(
+            "pub struct S<T>(T); impl<T> Clone for S<T> { fn clone(&self) -> S<T> { unimplemented!() } }",
+            "pub struct S<T>(T); impl<U> ::std::clone::Clone for self::S<U> { fn clone(&self) -> S<U> { unimplemented!() } }");
+        assert!(findings(&r).is_empty());
+        let r = compare_sources(
+            "use std::fmt; pub struct S<T>(T); impl<T> Clone for S<T> where T: fmt::Debug { fn clone(&self) -> S<T> { unimplemented!() } }",
+            "use std::fmt::Debug; pub struct S<T>(T); impl<T: Debug> Clone for S<T> { fn clone(&self) -> S<T> { unimplemented!() } }");
+        assert!(findings(&r).is_empty());
+        let r = compare_sources(
+            "pub trait Debug {} pub struct S<T>(T); impl<T: Debug> Clone for S<T> { fn clone(&self) -> S<T> { unimplemented!() } }",
+            "pub trait Debug {} pub struct S<T>(T); impl<T: ::std::fmt::Debug> Clone for S<T> { fn clone(&self) -> S<T> { unimplemented!() } }");
+        assert_eq!(findings(&r), vec![(ImplRemoved, Major), (ImplAdded, Breaking)]);
+
+        // impls for types downstream crates cannot name are not part of the API
+        let r = compare_sources("struct P;", "#[derive(Clone)] struct P;");
+        assert!(findings(&r).is_empty());
+        // even if a public type elsewhere has the same name
+        let r = compare_sources(
+            "pub struct P; mod imp { pub struct P; }",
+            "pub struct P; mod imp { #[derive(Clone)] pub struct P; }");
+        assert!(findings(&r).is_empty());
+        // but types re-exported from private modules are visible
+        let r = compare_sources(
+            "mod imp { pub struct P; } pub use imp::*;",
+            "mod imp { #[derive(Clone)] pub struct P; } pub use imp::*;");
+        assert_eq!(findings(&r), vec![(ImplAdded, Breaking)]);
+    }
+
+    #[test]
+    fn trait_impl_paths() {
+        // reported at the resolved paths of the self type and the trait
+        let mut r = compare_sources(
+            "pub mod inner { pub struct S; impl Clone for S { fn clone(&self) -> S { S } } }",
+            "pub mod inner { pub struct S; }");
+        suppress(&mut r, "inner::S as std::clone::Clone", "impl-removed");
+        assert_eq!(findings(&r), vec![(ImplRemoved, Note)]);
+
+        let mut r = compare_sources("pub mod inner { pub struct S; }", "pub mod inner { #[derive(Clone)] pub struct S; }");
+        suppress(&mut r, "inner::*", "impl-added");
+        assert_eq!(findings(&r), vec![(ImplAdded, Note)]);
+    }
+
+    #[test]
+    fn inherent_items() {
+        let r = compare_sources("pub struct S; impl S { pub fn a(&self) {} }", "pub struct S; impl S { pub fn a(&self) {} pub fn b(&self) {} }");
+        assert_eq!(findings(&r), vec![(ItemAdded, Breaking)]);
+        let r = compare_sources("pub struct S; impl S { pub fn a(&self) {} }", "pub struct S; impl S {}");
+        assert_eq!(findings(&r), vec![(ItemRemoved, Major)]);
+        let r = compare_sources("pub struct S; impl S { pub fn a(&self) {} }", "pub struct S; impl S { fn a(&self) {} }");
+        assert_eq!(findings(&r), vec![(MadePrivate, Major)]);
+        let r = compare_sources("pub struct S; impl S { pub fn a(&self) {} }", "pub struct S; impl S { pub fn a(&self, _: u8) {} }");
+        assert_eq!(findings(&r), vec![(ArgCountChanged, Major)]);
+        let r = compare_sources("pub struct S; impl S { pub fn a() {} }", "pub struct S; impl S { pub const a: u8 = 0; }");
+        assert_eq!(findings(&r), vec![(ItemKindChanged, Major)]);
+
+        // items are matched by self type and name, not by impl block
+        let r = compare_sources(
+            "pub struct S; impl S { pub fn a(&self) {} pub fn b(&self) {} }",
+            "pub struct S; impl S { pub fn a(&self) {} } impl S { pub fn b(&self) {} }");
+        assert!(findings(&r).is_empty());
+
+        // nor by how the self type is written
+        let r = compare_sources(
+            "pub struct S<T>(T); impl<T> S<T> { pub fn get(&self) -> &T { &self.0 } }",
+            "pub struct S<T>(T); impl<U> self::S<U> { pub fn get(&self) -> &U { &self.0 } }");
+        assert!(findings(&r).is_empty());
+        let r = compare_sources(
+            "pub struct S<T>(T); pub type Byte = S<u8>; impl S<u8> { pub fn a(&self) {} }",
+            "pub struct S<T>(T); pub type Byte = S<u8>; impl Byte { pub fn a(&self) {} }");
+        assert!(findings(&r).is_empty());
+        let r = compare_sources(
+            "pub struct S<T>(T); impl S<u8> { pub fn a(&self) {} }",
+            "pub struct S<T>(T); impl S<u16> { pub fn a(&self) {} }");
+        assert_eq!(findings(&r), vec![(ItemRemoved, Major), (ItemAdded, Breaking)]);
+    }
+
+    #[test]
+    fn inherent_item_paths() {
+        // reported at the resolved path of the self type, however it is written
+        let mut r = compare_sources(
+            "pub mod inner { pub struct S; } impl self::inner::S { pub fn a(&self) {} }",
+            "pub mod inner { pub struct S; }");
+        suppress(&mut r, "inner::S::a", "item-removed");
+        assert_eq!(findings(&r), vec![(ItemRemoved, Note)]);
+
+        let mut r = compare_sources(
+            "pub struct S; impl S { pub fn a(&self) {} }",
+            "pub struct S; impl S { pub fn a(&self) {} pub fn b(&self) {} }");
+        suppress(&mut r, "S::a", "item-added");
+        assert_eq!(findings(&r), vec![(ItemAdded, Breaking)]);
+    }
+}
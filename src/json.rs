@@ -0,0 +1,159 @@
+//! JSON report output
+//!
+//! The output is an object with a summary of the report at the top level and
+//! the report tree under `"report"`. `FORMAT_VERSION` is incremented whenever
+//! the format changes in a way which could break consumers; adding new keys
+//! does not count.
+
+use std::cmp::max;
+use std::collections::BTreeMap;
+
+use rustc_serialize::json::{Json, ToJson};
+
+use matrix::Matrix;
+use report::*;
+use version::{self, Bump, Version};
+
+/// The version of the JSON output format.
+pub const FORMAT_VERSION: u64 = 1;
+
+/// Serialize a report, along with a summary of the version bump it requires.
+///
+/// If the version of the old crate is known, the suggested next version is
+/// included as well.
+pub fn report_to_json(report: &Report, current_version: Option<&Version>) -> Json {
+    let severity = report.highest_severity();
+    let change_severity = report.highest_change_severity();
+    let mut obj = summary(severity, change_severity, current_version);
+    obj.insert("report".to_owned(), node_to_json(report, None));
+    Json::Object(obj)
+}
+
+/// Serialize the results of comparing in several configurations, with the
+/// same summary as `report_to_json` for all of them together and for each.
+///
+/// Problems reading the crates are under `"report"`, and each configuration
+/// is an entry of `"configurations"`.
+pub fn matrix_to_json(report: &Report, matrix: Option<&Matrix>, current_version: Option<&Version>) -> Json {
+    let severity = max(report.highest_severity(), matrix.map_or(Severity::Note, Matrix::highest_severity));
+    let change_severity = max(report.highest_change_severity(),
+        matrix.map_or(Severity::Note, Matrix::highest_change_severity));
+    let mut obj = summary(severity, change_severity, current_version);
+    obj.insert("report".to_owned(), node_to_json(report, None));
+    let mut configurations = Vec::new();
+    if let Some(matrix) = matrix {
+        for (target, row) in matrix.targets.iter().zip(&matrix.reports) {
+            for (feature_set, report) in matrix.feature_sets.iter().zip(row) {
+                let mut cell = BTreeMap::new();
+                cell.insert("target".to_owned(), target.triple.to_json());
+                cell.insert("features".to_owned(), feature_set.name.to_json());
+                cell.insert("highest_severity".to_owned(), report.highest_severity().to_json());
+                cell.insert("required_bump".to_owned(), Bump::from_severity(report.highest_change_severity()).to_json());
+                cell.insert("report".to_owned(), node_to_json(report, None));
+                configurations.push(Json::Object(cell));
+            }
+        }
+    }
+    obj.insert("configurations".to_owned(), Json::Array(configurations));
+    Json::Object(obj)
+}
+
+/// The summary at the top level of the output: the highest severities, and
+/// the version bump and next version they call for.
+fn summary(severity: Severity, change_severity: Severity, current_version: Option<&Version>) -> BTreeMap<String, Json> {
+    let mut obj = BTreeMap::new();
+    obj.insert("format_version".to_owned(), FORMAT_VERSION.to_json());
+    obj.insert("highest_severity".to_owned(), severity.to_json());
+    obj.insert("required_bump".to_owned(), Bump::from_severity(change_severity).to_json());
+    obj.insert("current_version".to_owned(), current_version.map(|v| v.to_string()).to_json());
+    obj.insert("suggested_version".to_owned(), current_version
+        .and_then(|v| version::suggest(v, change_severity))
+        .map(|v| v.to_string())
+        .to_json());
+    obj
+}
+
+/// Serialize a node of the report tree. Entries without a path of their own
+/// have the path of the nearest entry above them which does.
+fn node_to_json(report: &Report, parent_path: Option<&str>) -> Json {
+    let path = report.item.path.as_ref().map(|p| &**p).or(parent_path);
+    let mut obj = BTreeMap::new();
+    obj.insert("severity".to_owned(), report.item.severity.to_json());
+    obj.insert("strictness".to_owned(), report.item.strict.to_json());
+    obj.insert("text".to_owned(), report.item.text.to_json());
+    obj.insert("kind".to_owned(), report.item.kind.map_or(Json::Null, |kind| kind.id().to_json()));
+    obj.insert("path".to_owned(), path.map_or(Json::Null, str::to_json));
+    obj.insert("old_location".to_owned(), report.item.old_location.to_json());
+    obj.insert("new_location".to_owned(), report.item.new_location.to_json());
+    obj.insert("suppressed".to_owned(), report.item.suppressed.to_json());
+    obj.insert("children".to_owned(), Json::Array(report.children.iter()
+        .map(|child| node_to_json(child, path))
+        .collect()));
+    Json::Object(obj)
+}
+
+impl ToJson for Location {
+    fn to_json(&self) -> Json {
+        let mut obj = BTreeMap::new();
+        obj.insert("file".to_owned(), self.file.to_json());
+        obj.insert("line".to_owned(), self.line.to_json());
+        obj.insert("column".to_owned(), self.column.to_json());
+        Json::Object(obj)
+    }
+}
+
+impl ToJson for Suppressed {
+    fn to_json(&self) -> Json {
+        let mut obj = BTreeMap::new();
+        obj.insert("severity".to_owned(), self.severity.to_json());
+        obj.insert("reason".to_owned(), self.reason.to_json());
+        Json::Object(obj)
+    }
+}
+
+impl ToJson for Severity {
+    fn to_json(&self) -> Json {
+        Json::String(format!("{:?}", self).to_lowercase())
+    }
+}
+
+impl ToJson for Strictness {
+    fn to_json(&self) -> Json {
+        Json::String(format!("{:?}", self).to_lowercase())
+    }
+}
+
+impl ToJson for Bump {
+    fn to_json(&self) -> Json {
+        Json::String(format!("{:?}", self).to_lowercase())
+    }
+}
+
+#[test]
+fn report_shape() {
+    let mut r = Report::new();
+    push!(r, Minor(ItemAdded)).with_path("foo".to_owned());
+    let json = report_to_json(&r, Some(&Version::parse("1.2.3").unwrap()));
+
+    assert_eq!(json.find("format_version"), Some(&Json::U64(FORMAT_VERSION)));
+    assert_eq!(json.find("highest_severity"), Some(&Json::String("minor".to_owned())));
+    assert_eq!(json.find("required_bump"), Some(&Json::String("minor".to_owned())));
+    assert_eq!(json.find("current_version"), Some(&Json::String("1.2.3".to_owned())));
+    assert_eq!(json.find("suggested_version"), Some(&Json::String("1.3.0".to_owned())));
+
+    let root = json.find("report").unwrap();
+    assert_eq!(root.find("kind"), Some(&Json::Null));
+    let children = root.find("children").and_then(Json::as_array).unwrap();
+    assert_eq!(children.len(), 1);
+    assert_eq!(children[0].find("severity"), Some(&Json::String("minor".to_owned())));
+    assert_eq!(children[0].find("kind"), Some(&Json::String("item-added".to_owned())));
+    assert_eq!(children[0].find("path"), Some(&Json::String("foo".to_owned())));
+    assert_eq!(children[0].find("suppressed"), Some(&Json::Null));
+
+    // the matrix output has the same summary
+    let matrix = matrix_to_json(&r, None, Some(&Version::parse("1.2.3").unwrap()));
+    for key in &["format_version", "highest_severity", "required_bump", "current_version", "suggested_version"] {
+        assert_eq!(matrix.find(key), json.find(key));
+    }
+    assert_eq!(matrix.find("configurations"), Some(&Json::Array(Vec::new())));
+}
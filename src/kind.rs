@@ -0,0 +1,131 @@
+//! kinds of changes
+//!
+//! Every finding in a report is tagged with the kind of change it is. Kinds
+//! have stable string IDs, which are used in JSON output and suppression
+//! files, so existing IDs must never be renamed or reused.
+
+macro_rules! change_kinds {
+    ($($name:ident = $id:expr, $description:expr;)*) => {
+        /// The kind of a change found between two versions of a crate.
+        #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+        pub enum ChangeKind {
+            $($name,)*
+        }
+
+        impl ChangeKind {
+            /// Every kind of change, in declaration order.
+            pub fn all() -> &'static [ChangeKind] {
+                const ALL: &'static [ChangeKind] = &[$(ChangeKind::$name,)*];
+                ALL
+            }
+
+            /// The stable identifier of this kind of change.
+            pub fn id(&self) -> &'static str {
+                match *self {
+                    $(ChangeKind::$name => $id,)*
+                }
+            }
+
+            /// A short description of this kind of change, which is used as
+            /// the text of findings without further detail.
+            pub fn description(&self) -> &'static str {
+                match *self {
+                    $(ChangeKind::$name => $description,)*
+                }
+            }
+
+            /// Look up a kind of change by its identifier.
+            pub fn from_id(id: &str) -> Option<ChangeKind> {
+                ChangeKind::all().iter().cloned().find(|kind| kind.id() == id)
+            }
+        }
+    }
+}
+
+change_kinds! {
+    // Crates
+    CrateCfgNarrowed = "crate-cfg-narrowed", "crate #[cfg] coverage reduced";
+    CrateCfgWidened = "crate-cfg-widened", "crate #[cfg] coverage increased";
+    FeatureRemoved = "feature-removed", "Cargo feature removed";
+    FeatureAdded = "feature-added", "Cargo feature added";
+    DefaultFeatureRemoved = "default-feature-removed", "feature no longer enabled by default";
+    DefaultFeatureAdded = "default-feature-added", "feature now enabled by default";
+
+    // Items in general
+    ItemRemoved = "item-removed", "removed";
+    ItemAdded = "item-added", "added";
+    MadePrivate = "made-private", "made private";
+    MadePublic = "made-public", "made public";
+    ItemKindChanged = "item-kind-changed", "replaced by a different kind of item";
+    CfgNarrowed = "cfg-narrowed", "availability narrowed";
+    CfgWidened = "cfg-widened", "availability widened";
+    ReexportChanged = "reexport-changed", "re-export refers to a different path";
+
+    // Types of type aliases, consts, statics, fields and associated items
+    TypeChanged = "type-changed", "type changed";
+    TypeGeneralized = "type-generalized", "type generalized";
+    ConstReplacedByStatic = "const-replaced-by-static", "const replaced by static";
+    StaticReplacedByConst = "static-replaced-by-const", "static replaced by const";
+    MutabilityChanged = "mutability-changed", "mutability changed";
+    ConstQualifierRemoved = "const-qualifier-removed", "const qualifier removed";
+
+    // Generics
+    LifetimeParamsChanged = "lifetime-params-changed", "lifetime parameter count changed";
+    TypeParamRemoved = "type-param-removed", "type parameter removed";
+    TypeParamAdded = "type-param-added", "type parameter added";
+    DefaultedTypeParamAdded = "defaulted-type-param-added", "defaulted type parameter added";
+    TypeParamDefaultRemoved = "type-param-default-removed", "type parameter default removed";
+    TypeParamDefaultAdded = "type-param-default-added", "type parameter default added";
+    TypeParamDefaultChanged = "type-param-default-changed", "type parameter default changed";
+    BoundsTightened = "bounds-tightened", "bounds tightened";
+    BoundsLoosened = "bounds-loosened", "bounds loosened";
+
+    // Structs and enums
+    StructKindChanged = "struct-kind-changed", "struct kind changed";
+    FieldRemoved = "field-removed", "field removed";
+    FieldAdded = "field-added", "field added";
+    PublicFieldAdded = "public-field-added", "public field added";
+    PrivateFieldsChanged = "private-fields-changed", "private fields added or removed";
+    VariantAdded = "variant-added", "variant added";
+    VariantKindChanged = "variant-kind-changed", "variant kind changed";
+
+    // Traits
+    TraitUnsafetyChanged = "trait-unsafety-changed", "trait unsafety changed";
+    SupertraitRemoved = "supertrait-removed", "supertrait removed";
+    SupertraitAdded = "supertrait-added", "supertrait added";
+    TraitItemAdded = "trait-item-added", "non-defaulted item added";
+    DefaultedTraitItemAdded = "defaulted-trait-item-added", "defaulted item added";
+    TraitItemDefaultRemoved = "trait-item-default-removed", "default removed";
+    TraitItemDefaultAdded = "trait-item-default-added", "default added";
+    AssocTypeBoundsChanged = "assoc-type-bounds-changed", "associated type bounds changed";
+
+    // Functions
+    FnUnsafetyChanged = "fn-unsafety-changed", "unsafety changed";
+    FnAbiChanged = "fn-abi-changed", "ABI changed";
+    ArgCountChanged = "arg-count-changed", "argument count changed";
+    ArgTypeChanged = "arg-type-changed", "argument type changed";
+    ArgGeneralized = "arg-generalized", "argument generalized";
+    ReturnTypeChanged = "return-type-changed", "return type changed";
+
+    // Trait implementations
+    ImplRemoved = "impl-removed", "removed";
+    ImplAdded = "impl-added", "added";
+    FundamentalImplAdded = "fundamental-impl-added", "fundamental trait implemented";
+    NegativeImplAdded = "negative-impl-added", "negative impl added";
+
+    // Macros
+    MacroArmRemoved = "macro-arm-removed", "macro arm removed";
+    MacroArmAdded = "macro-arm-added", "macro arm added";
+    MacroArmNarrowed = "macro-arm-narrowed", "macro arm narrowed";
+    MacroArmWidened = "macro-arm-widened", "macro arm widened";
+
+    // Versions
+    VersionBumpInsufficient = "version-bump-insufficient", "version not bumped enough";
+}
+
+#[test]
+fn ids_round_trip() {
+    for &kind in ChangeKind::all() {
+        assert_eq!(ChangeKind::from_id(kind.id()), Some(kind));
+    }
+}
@@ -6,58 +6,169 @@ extern crate syntax_ext;
 extern crate rustc;
 extern crate rustc_resolve;
 extern crate rustc_errors;
+extern crate rustc_serialize;
+extern crate flate2;
+extern crate semver;
+extern crate tar;
+extern crate tempdir;
+extern crate toml;
 
 #[macro_use]
 pub mod report;
 mod compare;
 mod cfg;
+mod cfg_attr;
+pub mod features;
+mod impls;
+pub mod kind;
+pub mod json;
+mod macros;
+pub mod manifest;
+pub mod matrix;
+mod resolve;
+mod sat;
+pub mod source;
+pub mod suppress;
+pub mod targets;
+mod types;
+pub mod version;
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
 use syntax::ast::{self, Crate};
+use syntax::codemap::{CodeMap, Span};
 
 pub use compare::compare_crates;
 
-pub fn parse_crate(file: &Path) -> Option<Crate> {
-    use std::rc::Rc;
-    use syntax::codemap::CodeMap;
+/// Find the root source file of the crate at the given path.
+///
+/// The path may either be the root file itself, or a package directory. For
+/// the latter, the crate root is located through its Cargo.toml if it has
+/// one, or otherwise is `src/lib.rs` or `src/main.rs`.
+pub fn crate_root(path: &Path) -> Option<PathBuf> {
+    if path.is_file() {
+        return Some(path.to_owned())
+    }
+    let manifest = path.join("Cargo.toml");
+    if let Ok(manifest) = manifest::Manifest::load(&manifest) {
+        return manifest.crate_root()
+    }
+    ["src/lib.rs", "src/main.rs"].iter()
+        .map(|file| path.join(file))
+        .find(|file| file.is_file())
+}
+
+/// A parsed crate, along with what is needed to locate its items in source.
+pub struct ParsedCrate {
+    pub krate: Crate,
+    pub codemap: Rc<CodeMap>,
+    /// The directory containing the crate root, which locations are given
+    /// relative to
+    pub base: PathBuf,
+    /// The package's Cargo features, if it has a Cargo.toml
+    pub features: Option<features::Features>,
+}
+
+impl ParsedCrate {
+    /// Find the source location of a span, if it has one.
+    pub fn locate(&self, span: Span) -> Option<report::Location> {
+        if span.lo.0 == 0 && span.hi.0 == 0 {
+            return None
+        }
+        let loc = self.codemap.lookup_char_pos(span.lo);
+        let path = Path::new(&loc.file.name);
+        Some(report::Location {
+            file: path.strip_prefix(&self.base).unwrap_or(path).display().to_string(),
+            line: loc.line,
+            column: loc.col.0 + 1,
+        })
+    }
+}
+
+/// Parse and expand the crate at the given path.
+///
+/// Out-of-line modules (`mod foo;`) are loaded relative to the file they are
+/// declared in, honoring `#[path]` attributes. Any problems encountered along
+/// the way, such as missing module files, are placed in the report.
+pub fn parse_crate(r: &mut report::Report, path: &Path) -> Option<ParsedCrate> {
+    use std::cell::RefCell;
     use syntax::parse::parser::Parser;
     use syntax::parse::{lexer, ParseSess};
     use syntax::errors::Handler;
-    use syntax::errors::emitter::ColorConfig;
     use rustc::session;
     use rustc_resolve::Resolver;
 
-    // Set up the parser environment and read a crate
+    let r = push!(r, Lazy Note, "Reading crate at {}", path.display());
+    let file = match crate_root(path) {
+        Some(file) => file,
+        None => {
+            push!(r, Error, "No crate root found: expected a source file or a directory containing src/lib.rs or src/main.rs");
+            return None
+        }
+    };
+
+    // Set up the parser environment, collecting diagnostics for the report
     let cm = Rc::new(CodeMap::new());
-    let sh = Handler::with_tty_emitter(ColorConfig::Auto, None, false, false, Some(cm.clone()));
-    let ps = ParseSess::with_span_handler(sh, cm);
-    let fm = ps.codemap().load_file(file).unwrap();
+    let diagnostics = Rc::new(RefCell::new(Vec::new()));
+    let emitter = Diagnostics { codemap: cm.clone(), messages: diagnostics.clone() };
+    let sh = Handler::with_emitter(true, false, Box::new(emitter));
+    let ps = ParseSess::with_span_handler(sh, cm.clone());
+
+    // Read the crate, following out-of-line modules
+    let fm = match ps.codemap().load_file(&file) {
+        Ok(fm) => fm,
+        Err(e) => {
+            push!(r, Error, "Failed to read {}: {}", file.display(), e);
+            return None
+        }
+    };
     let reader = lexer::StringReader::new(&ps.span_diagnostic, fm);
     let mut p = Parser::new(&ps, Vec::new(), Box::new(reader));
     let krate = match p.parse_crate_mod() {
         Ok(c) => c,
-        Err(_) => return None,
+        Err(mut e) => {
+            e.emit();
+            report_diagnostics(r, &diagnostics.borrow());
+            push!(r, Error, "Failed to parse crate");
+            return None
+        }
     };
 
     // I have been informed that things go wrong if you don't perform
     // configuration before macro expansion. In want of a real solution, this
-    // advice is being blatantly ignored.
+    // advice is being blatantly ignored, except for #[cfg_attr], which is
+    // turned into #[cfg] on the items it applies to.
+    let krate = cfg_attr::expand_cfg_attrs(&ps, krate);
 
     // Expand syntax extensions ahead of time
-    let ecfg = syntax::ext::expand::ExpansionConfig::default("__".to_string());
+    let features = syntax::feature_gate::Features::new();
+    let mut ecfg = syntax::ext::expand::ExpansionConfig::default("__".to_string());
+    ecfg.features = Some(&features);
     let mut ml = syntax::ext::base::DummyMacroLoader; // TODO: replace with loading from other crates
     let mut ext_ctx = syntax::ext::base::ExtCtxt::new(&ps, krate.config.clone(), ecfg, &mut ml);
     // register built-in #[derive]s, so we can detect them as trait impls
     syntax_ext::deriving::register_all(&mut ext_ctx.syntax_env);
+    // macros from other crates are not loaded, so they expand to nothing
+    for name in undefined_macros(&krate) {
+        if ext_ctx.syntax_env.find(name).is_none() {
+            if !STD_MACROS.contains(&&*name.as_str()) {
+                push!(r, Warning, "Macro {}! is not defined in the crate, so its uses are ignored", name);
+            }
+            let expander: syntax::ext::base::MacroExpanderFn = expand_nothing;
+            ext_ctx.syntax_env.insert(name, syntax::ext::base::NormalTT(Box::new(expander), None, false));
+        }
+    }
     // perform the expansion
-    let (krate, macro_names) = syntax::ext::expand::expand_crate(ext_ctx, vec![], krate);
+    let krate = syntax::ext::expand::expand_crate(&mut ext_ctx, vec![], krate);
+    let krate = cfg_attr::configure_derives(krate);
+    report_diagnostics(r, &diagnostics.borrow());
 
     // Assign node IDs: finishes expanding certain macro nodes
     let session = session::build_session(
         session::config::basic_options(),
         &rustc::dep_graph::DepGraph::new(false),
-        Some(file.to_owned()),
+        Some(file.clone()),
         rustc_errors::registry::Registry::new(&[]),
         Rc::new(rustc::middle::cstore::DummyCrateStore),
     );
@@ -65,20 +176,149 @@ pub fn parse_crate(file: &Path) -> Option<Crate> {
     let mut resolver = Resolver::new(&session, rustc_resolve::MakeGlobMap::No, &resolver_arenas);
     let krate = resolver.assign_node_ids(krate);
 
-    Some(krate)
+    // Express #[cfg(feature)] in terms of the features a user can request
+    let features = match manifest::Manifest::find(path).map(|m| manifest::Manifest::load(&m)) {
+        None => None,
+        Some(Ok(manifest)) => Some(features::Features::from_manifest(&manifest)),
+        Some(Err(e)) => {
+            push!(r, Error, e);
+            None
+        }
+    };
+    let krate = match features {
+        Some(ref features) => features.apply(krate),
+        None => krate,
+    };
+
+    Some(ParsedCrate {
+        krate: krate,
+        codemap: cm,
+        base: file.parent().map_or(PathBuf::new(), Path::to_owned),
+        features: features,
+    })
 }
 
-pub fn create_report(old: &Path, new: &Path) -> report::Report {
-    let old_crate = parse_crate(old);
-    let new_crate = parse_crate(new);
+/// Macros exported by the standard library, and those built into the
+/// compiler. They are never defined in the crate itself, so those which the
+/// expander does not provide, such as `concat!`, expand to nothing without
+/// a warning.
+const STD_MACROS: &'static [&'static str] = &[
+    // std
+    "assert", "assert_eq", "assert_ne", "debug_assert", "debug_assert_eq",
+    "debug_assert_ne", "format", "panic", "print", "println", "select",
+    "thread_local", "try", "unimplemented", "unreachable", "vec", "write",
+    "writeln",
+    // compiler built-ins
+    "asm", "cfg", "column", "concat", "concat_idents", "env", "file",
+    "format_args", "include", "include_bytes", "include_str", "line",
+    "log_syntax", "module_path", "option_env", "stringify", "trace_macros",
+];
 
-    let mut report = report::Report::new();
-    if old_crate.is_none() {
-        push!(report, Error, "Failed to read crate at {}", old.display());
+/// Find the macros which a crate invokes but does not define with
+/// `macro_rules!`, including those invoked from the bodies of its macros.
+fn undefined_macros(krate: &Crate) -> Vec<ast::Name> {
+    use std::collections::BTreeSet;
+    use syntax::parse::token::{self, Token};
+    use syntax::tokenstream::TokenTree;
+    use syntax::visit::{self, Visitor};
+
+    struct MacroNames {
+        invoked: BTreeSet<ast::Name>,
+        defined: BTreeSet<ast::Name>,
+    }
+
+    impl MacroNames {
+        fn visit_tts(&mut self, tts: &[TokenTree]) {
+            for (i, tt) in tts.iter().enumerate() {
+                match *tt {
+                    TokenTree::Token(_, ref token @ Token::Ident(_)) if token.is_any_keyword() => {}
+                    TokenTree::Token(_, Token::Ident(ident)) => match (tts.get(i + 1), tts.get(i + 2)) {
+                        (Some(&TokenTree::Token(_, Token::Not)), Some(&TokenTree::Token(_, Token::Ident(name))))
+                            if ident.name.as_str() == "macro_rules" => { self.defined.insert(name.name); }
+                        (Some(&TokenTree::Token(_, Token::Not)), _) => { self.invoked.insert(ident.name); }
+                        _ => {}
+                    },
+                    TokenTree::Delimited(_, ref delimited) => self.visit_tts(&delimited.tts),
+                    TokenTree::Sequence(_, ref sequence) => self.visit_tts(&sequence.tts),
+                    TokenTree::Token(..) => {}
+                }
+            }
+        }
+    }
+
+    impl Visitor for MacroNames {
+        fn visit_item(&mut self, item: &ast::Item) {
+            if let ast::ItemKind::Mac(ref mac) = item.node {
+                if mac.node.path.segments.len() == 1 && mac.node.path.segments[0].identifier.name.as_str() == "macro_rules" {
+                    self.defined.insert(item.ident.name);
+                }
+            }
+            visit::walk_item(self, item)
+        }
+
+        fn visit_mac(&mut self, mac: &ast::Mac) {
+            if let Some(segment) = mac.node.path.segments.last() {
+                self.invoked.insert(segment.identifier.name);
+            }
+            self.visit_tts(&mac.node.tts);
+        }
+    }
+
+    let mut names = MacroNames { invoked: BTreeSet::new(), defined: BTreeSet::new() };
+    visit::walk_crate(&mut names, krate);
+    names.defined.insert(token::intern("macro_rules"));
+    names.invoked.difference(&names.defined).cloned().collect()
+}
+
+/// Expand a macro invocation to nothing.
+fn expand_nothing<'cx>(_: &'cx mut syntax::ext::base::ExtCtxt, span: Span, _: &[syntax::tokenstream::TokenTree])
+    -> Box<syntax::ext::base::MacResult + 'cx>
+{
+    syntax::ext::base::DummyResult::any(span)
+}
+
+/// An `Emitter` which keeps diagnostics for placing in the report.
+struct Diagnostics {
+    codemap: Rc<CodeMap>,
+    messages: Rc<std::cell::RefCell<Vec<(report::Severity, String)>>>,
+}
+
+impl syntax::errors::emitter::Emitter for Diagnostics {
+    fn emit(&mut self, db: &syntax::errors::DiagnosticBuilder) {
+        use syntax::errors::Level;
+
+        let severity = match db.level {
+            Level::Warning => report::Severity::Warning,
+            Level::Note | Level::Help | Level::Cancelled => return,
+            _ => report::Severity::Error,
+        };
+        let text = match db.span.primary_span() {
+            Some(span) => format!("{}: {}", self.codemap.span_to_string(span), db.message),
+            None => db.message.clone(),
+        };
+        self.messages.borrow_mut().push((severity, text));
     }
-    if new_crate.is_none() {
-        push!(report, Error, "Failed to read crate at {}", new.display());
+}
+
+fn report_diagnostics(r: &mut report::Report, messages: &[(report::Severity, String)]) {
+    for &(severity, ref text) in messages {
+        r.push(report::ReportItem {
+            strict: report::Strictness::Strict,
+            severity: severity,
+            text: text.clone().into(),
+            kind: None,
+            path: None,
+            old_location: None,
+            new_location: None,
+            suppressed: None,
+        });
     }
+}
+
+pub fn create_report(old: &Path, new: &Path) -> report::Report {
+    let mut report = report::Report::new();
+    let old_crate = parse_crate(&mut report, old);
+    let new_crate = parse_crate(&mut report, new);
     if let (Some(old), Some(new)) = (old_crate, new_crate) {
         compare_crates(&mut report, &old, &new);
     }
@@ -87,6 +327,43 @@ pub fn create_report(old: &Path, new: &Path) -> report::Report {
     report
 }
 
+/// Compare the crates at the given paths separately in each combination of
+/// the given targets and feature sets. Problems reading the crates are placed
+/// in the returned report.
+pub fn create_matrix(old: &Path, new: &Path, targets: Vec<&'static targets::Target>,
+    feature_sets: Vec<matrix::FeatureSet>) -> (report::Report, Option<matrix::Matrix>)
+{
+    let mut report = report::Report::new();
+    let old_crate = parse_crate(&mut report, old);
+    let new_crate = parse_crate(&mut report, new);
+    let matrix = match (old_crate, new_crate) {
+        (Some(old), Some(new)) => Some(matrix::compare_matrix(&old, &new, targets, feature_sets)),
+        _ => None,
+    };
+
+    report.strip_lazy();
+    (report, matrix)
+}
+
+/// Read the `[package] version` from the Cargo.toml belonging to the crate
+/// at the given path, reporting an `Error` if it cannot be found.
+pub fn crate_version(r: &mut report::Report, path: &Path) -> Option<version::Version> {
+    let manifest = match manifest::Manifest::find(path) {
+        Some(manifest) => manifest,
+        None => {
+            push!(r, Error, "No Cargo.toml found for crate at {}", path.display());
+            return None
+        }
+    };
+    match manifest::Manifest::load(&manifest).and_then(|m| m.version()) {
+        Ok(version) => Some(version),
+        Err(e) => {
+            push!(r, Error, e);
+            None
+        }
+    }
+}
+
 fn fmt_path(path: &ast::Path) -> String {
     use std::fmt::Write;
 
@@ -102,3 +379,44 @@ fn fmt_path(path: &ast::Path) -> String {
     }
     res
 }
+
+/// Write a crate made of the given files into a directory, for tests.
+#[cfg(test)]
+fn write_crate(dir: &Path, files: &[(&str, &str)]) {
+    use std::fs::{self, File};
+    use std::io::Write;
+
+    for &(name, contents) in files {
+        let file = dir.join(name);
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        File::create(&file).unwrap().write_all(contents.as_bytes()).unwrap();
+    }
+}
+
+/// Compare two versions of a crate given as the source of `src/lib.rs`, for
+/// tests.
+#[cfg(test)]
+pub fn compare_sources(old: &str, new: &str) -> report::Report {
+    compare_files(&[("src/lib.rs", old)], &[("src/lib.rs", new)])
+}
+
+/// Compare two versions of a crate given as the files making it up, for
+/// tests.
+#[cfg(test)]
+pub fn compare_files(old: &[(&str, &str)], new: &[(&str, &str)]) -> report::Report {
+    let dir = tempdir::TempDir::new("semverify").unwrap();
+    let (old_dir, new_dir) = (dir.path().join("old"), dir.path().join("new"));
+    write_crate(&old_dir, old);
+    write_crate(&new_dir, new);
+    create_report(&old_dir, &new_dir)
+}
+
+/// The kind and severity of every finding in a report, for tests.
+#[cfg(test)]
+pub fn findings(r: &report::Report) -> Vec<(kind::ChangeKind, report::Severity)> {
+    let mut result: Vec<_> = r.item.kind.map(|kind| (kind, r.item.severity)).into_iter().collect();
+    for child in &r.children {
+        result.extend(findings(child));
+    }
+    result
+}
@@ -0,0 +1,329 @@
+//! exported macro comparison
+//!
+//! Macros have no signatures, so the best that can be done is to compare the
+//! matchers of their arms. Arms are compared structurally, with fragment
+//! specifiers ordered by how much they accept, e.g. `$x:expr` accepts
+//! everything `$x:ident` does. This is best-effort: it does not account for
+//! arm order, or for the follow-set restrictions on fragments.
+
+use std::fmt;
+
+use syntax::ast::*;
+use syntax::parse::token::{Token, BinOpToken};
+use syntax::print::pprust;
+use syntax::tokenstream::{TokenTree, KleeneOp};
+
+use report::*;
+use compare::Context;
+use types::Relation;
+
+/// A single element of a macro arm's matcher.
+#[derive(PartialEq, Eq, Debug)]
+enum Matcher {
+    /// A literal token, as printed
+    Token(String),
+    /// A `$name:kind` fragment, by its kind
+    Fragment(String),
+    /// A delimited group, by its opening delimiter
+    Group(String, Vec<Matcher>),
+    /// A `$(...) sep op` repetition
+    Repeat(Vec<Matcher>, Option<String>, KleeneOp),
+}
+
+pub fn compare_macros(r: &mut Report, cx: &Context, old: &[MacroDef], new: &[MacroDef]) {
+    // Major: removing an exported macro
+    for mac in old {
+        let new_mac = new.iter().find(|m| m.ident.name == mac.ident.name);
+        let r = push!(r, Note, "macro {}!", mac.ident.name)
+            .with_path(format!("{}!", mac.ident.name))
+            .with_locations(cx.old.locate(mac.span), new_mac.and_then(|m| cx.new.locate(m.span)));
+        match new_mac {
+            None => { push!(r, Major(ItemRemoved)); }
+            Some(new_mac) => match (parse_arms(&mac.body), parse_arms(&new_mac.body)) {
+                (Some(old_arms), Some(new_arms)) => compare_arms(r, &old_arms, &new_arms),
+                _ => { push!(r, Warning, "Could not parse macro arms"); }
+            },
+        }
+    }
+
+    // Minor: adding an exported macro
+    for mac in new {
+        if !old.iter().any(|m| m.ident.name == mac.ident.name) {
+            let r = push!(r, Lazy Note, "macro {}!", mac.ident.name)
+                .with_path(format!("{}!", mac.ident.name))
+                .with_locations(None, cx.new.locate(mac.span));
+            push!(r, Minor(ItemAdded));
+        }
+    }
+}
+
+// Major: removing an arm, or narrowing what it accepts.
+// Minor: adding an arm, or widening what it accepts.
+fn compare_arms(r: &mut Report, old: &[Vec<Matcher>], new: &[Vec<Matcher>]) {
+    for arm in old {
+        let relations: Vec<Relation> = new.iter().map(|n| compare_seqs(arm, n)).collect();
+        if relations.contains(&Relation::Equal) {
+            continue
+        } else if relations.contains(&Relation::MoreGeneral) {
+            push!(r, Minor(MacroArmWidened), "arm widened: ({})", Seq(arm));
+        } else if relations.contains(&Relation::LessGeneral) {
+            push!(r, Major(MacroArmNarrowed), "arm narrowed: ({})", Seq(arm));
+        } else {
+            push!(r, Major(MacroArmRemoved), "arm removed: ({})", Seq(arm));
+        }
+    }
+
+    for arm in new {
+        if old.iter().all(|o| compare_seqs(o, arm) == Relation::Incompatible) {
+            push!(r, Minor(MacroArmAdded), "arm added: ({})", Seq(arm));
+        }
+    }
+}
+
+fn compare_seqs(old: &[Matcher], new: &[Matcher]) -> Relation {
+    if old.len() != new.len() {
+        return Relation::Incompatible
+    }
+    old.iter().zip(new).fold(Relation::Equal, |rel, (o, n)| rel.and(compare_matchers(o, n)))
+}
+
+fn compare_matchers(old: &Matcher, new: &Matcher) -> Relation {
+    use self::Matcher::*;
+    match (old, new) {
+        (&Token(ref o), &Token(ref n)) => if o == n { Relation::Equal } else { Relation::Incompatible },
+        (&Fragment(ref o), &Fragment(ref n)) => {
+            if o == n {
+                Relation::Equal
+            } else if fragment_accepts(n, o) {
+                Relation::MoreGeneral
+            } else if fragment_accepts(o, n) {
+                Relation::LessGeneral
+            } else {
+                Relation::Incompatible
+            }
+        }
+        (&Token(ref o), &Fragment(ref n)) => if fragment_accepts_token(n, o) {
+            Relation::MoreGeneral
+        } else {
+            Relation::Incompatible
+        },
+        (&Fragment(ref o), &Token(ref n)) => if fragment_accepts_token(o, n) {
+            Relation::LessGeneral
+        } else {
+            Relation::Incompatible
+        },
+        (&Group(ref o_delim, ref o), &Group(ref n_delim, ref n)) => if o_delim == n_delim {
+            compare_seqs(o, n)
+        } else {
+            Relation::Incompatible
+        },
+        (&Repeat(ref o, ref o_sep, o_op), &Repeat(ref n, ref n_sep, n_op)) => {
+            if o_sep != n_sep {
+                return Relation::Incompatible
+            }
+            let op = match (o_op, n_op) {
+                (KleeneOp::OneOrMore, KleeneOp::ZeroOrMore) => Relation::MoreGeneral,
+                (KleeneOp::ZeroOrMore, KleeneOp::OneOrMore) => Relation::LessGeneral,
+                _ => Relation::Equal,
+            };
+            op.and(compare_seqs(o, n))
+        }
+        _ => Relation::Incompatible,
+    }
+}
+
+/// Whether a fragment of kind `wide` accepts everything one of kind `narrow` does.
+fn fragment_accepts(wide: &str, narrow: &str) -> bool {
+    match (wide, narrow) {
+        ("tt", "ident") |
+        ("expr", "ident") | ("expr", "path") | ("expr", "block") |
+        ("ty", "ident") | ("ty", "path") |
+        ("pat", "ident") | ("pat", "path") |
+        ("path", "ident") => true,
+        _ => wide == narrow,
+    }
+}
+
+/// Whether a fragment of kind `kind` accepts a particular literal token.
+fn fragment_accepts_token(kind: &str, token: &str) -> bool {
+    let is_ident = token.chars().next().map_or(false, |c| c.is_alphabetic() || c == '_') &&
+        token.chars().all(|c| c.is_alphanumeric() || c == '_');
+    match kind {
+        "tt" => true,
+        "ident" | "path" | "ty" | "expr" | "pat" => is_ident,
+        _ => false,
+    }
+}
+
+/// Split a `macro_rules!` body into the matchers of each of its arms.
+fn parse_arms(body: &[TokenTree]) -> Option<Vec<Vec<Matcher>>> {
+    let mut arms = Vec::new();
+    let mut iter = body.iter();
+    loop {
+        match iter.next() {
+            Some(&TokenTree::Delimited(_, ref matcher)) => arms.push(parse_matchers(&matcher.tts)),
+            None => return Some(arms),
+            _ => return None,
+        }
+        match (iter.next(), iter.next()) {
+            (Some(&TokenTree::Token(_, Token::FatArrow)), Some(&TokenTree::Delimited(..))) => {}
+            _ => return None,
+        }
+        if let Some(&TokenTree::Token(_, Token::Semi)) = iter.clone().next() {
+            iter.next();
+        }
+    }
+}
+
+fn parse_matchers(tts: &[TokenTree]) -> Vec<Matcher> {
+    let mut result = Vec::new();
+    let mut i = 0;
+    while i < tts.len() {
+        i += match tts[i] {
+            // Matchers which have already been through the macro parser
+            TokenTree::Token(_, Token::MatchNt(_, kind)) => {
+                result.push(Matcher::Fragment(kind.name.to_string()));
+                1
+            }
+            TokenTree::Sequence(_, ref seq) => {
+                result.push(Matcher::Repeat(
+                    parse_matchers(&seq.tts),
+                    seq.separator.as_ref().map(pprust::token_to_string),
+                    seq.op));
+                1
+            }
+            // Raw matchers, as they appear in the source
+            TokenTree::Token(_, Token::Dollar) => {
+                let (matcher, len) = parse_dollar(&tts[i + 1..]);
+                result.push(matcher);
+                len + 1
+            }
+            TokenTree::Token(_, ref tok) => {
+                result.push(Matcher::Token(pprust::token_to_string(tok)));
+                1
+            }
+            TokenTree::Delimited(_, ref delimited) => {
+                result.push(Matcher::Group(
+                    pprust::token_to_string(&Token::OpenDelim(delimited.delim)),
+                    parse_matchers(&delimited.tts)));
+                1
+            }
+        };
+    }
+    result
+}
+
+/// Parse the tokens following a `$`, returning the matcher and the number of
+/// tokens consumed.
+fn parse_dollar(tts: &[TokenTree]) -> (Matcher, usize) {
+    let token = |idx: usize| match tts.get(idx) {
+        Some(&TokenTree::Token(_, ref tok)) => Some(tok),
+        _ => None,
+    };
+
+    // $name:kind
+    if let (Some(&Token::Ident(_)), Some(&Token::Colon), Some(&Token::Ident(kind))) = (token(0), token(1), token(2)) {
+        return (Matcher::Fragment(kind.name.to_string()), 3)
+    }
+
+    // $(...) sep op
+    if let Some(&TokenTree::Delimited(_, ref delimited)) = tts.get(0) {
+        let inner = parse_matchers(&delimited.tts);
+        if let Some(op) = token(1).and_then(kleene_op) {
+            return (Matcher::Repeat(inner, None, op), 2)
+        }
+        if let (Some(sep), Some(op)) = (token(1), token(2).and_then(kleene_op)) {
+            return (Matcher::Repeat(inner, Some(pprust::token_to_string(sep)), op), 3)
+        }
+        return (Matcher::Group("$(".to_owned(), inner), 1)
+    }
+
+    (Matcher::Token("$".to_owned()), 0)
+}
+
+fn kleene_op(tok: &Token) -> Option<KleeneOp> {
+    match *tok {
+        Token::BinOp(BinOpToken::Star) => Some(KleeneOp::ZeroOrMore),
+        Token::BinOp(BinOpToken::Plus) => Some(KleeneOp::OneOrMore),
+        _ => None,
+    }
+}
+
+/// Displays a sequence of matchers in roughly their source form.
+struct Seq<'a>(&'a [Matcher]);
+
+impl<'a> fmt::Display for Seq<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let mut first = true;
+        for matcher in self.0 {
+            if first {
+                first = false;
+            } else {
+                try!(fmt.write_str(" "));
+            }
+            try!(match *matcher {
+                Matcher::Token(ref tok) => fmt.write_str(tok),
+                Matcher::Fragment(ref kind) => write!(fmt, "$_:{}", kind),
+                Matcher::Group(ref open, ref inner) => write!(fmt, "{}{}{}", open, Seq(inner), close_delim(open)),
+                Matcher::Repeat(ref inner, ref sep, op) => write!(fmt, "$({}){}{}",
+                    Seq(inner),
+                    sep.as_ref().map_or("", |s| &**s),
+                    match op { KleeneOp::ZeroOrMore => "*", KleeneOp::OneOrMore => "+" }),
+            });
+        }
+        Ok(())
+    }
+}
+
+fn close_delim(open: &str) -> &'static str {
+    match open {
+        "(" | "$(" => ")",
+        "[" => "]",
+        "{" => "}",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kind::ChangeKind::*;
+    use report::Severity::*;
+    use {compare_sources, findings};
+
+    #[test]
+    fn macro_arms() {
+        let ident = "#[macro_export] macro_rules! m { ($x:ident) => {} }";
+        let expr = "#[macro_export] macro_rules! m { ($x:expr) => {} }";
+        assert_eq!(findings(&compare_sources(ident, expr)), vec![(MacroArmWidened, Minor)]);
+        assert_eq!(findings(&compare_sources(expr, ident)), vec![(MacroArmNarrowed, Major)]);
+
+        let r = compare_sources(
+            "#[macro_export] macro_rules! m { () => {} }",
+            "#[macro_export] macro_rules! m { () => {}; ($x:expr) => {} }");
+        assert_eq!(findings(&r), vec![(MacroArmAdded, Minor)]);
+        let r = compare_sources(
+            "#[macro_export] macro_rules! m { () => {}; ($x:expr) => {} }",
+            "#[macro_export] macro_rules! m { () => {} }");
+        assert_eq!(findings(&r), vec![(MacroArmRemoved, Major)]);
+
+        // only exported macros are part of the API
+        let r = compare_sources(expr, "macro_rules! m { ($x:expr) => {} }");
+        assert_eq!(findings(&r), vec![(ItemRemoved, Major)]);
+    }
+
+    #[test]
+    fn macros_from_other_crates() {
+        // std and compiler macros are expected to be undefined
+        let source = "pub const NAME: &'static str = concat!(\"a\", stringify!(b), env!(\"NAME\"));
+                      pub fn f(x: u8) -> bool { assert_ne!(x, 0); debug_assert_ne!(x, 1); cfg!(unix) }
+                      pub fn g() -> Option<&'static str> { option_env!(\"NAME\") }
+                      pub fn h() -> String { format!(\"{}{}{}\", file!(), line!(), module_path!()) }";
+        let r = compare_sources(source, source);
+        assert!(findings(&r).is_empty());
+        assert!(r.highest_severity() < Warning);
+
+        // while others are ignored with a warning
+        let r = compare_sources("lazy_static! { static ref X: u8 = 0; } pub fn f() {}", "pub fn f() {}");
+        assert_eq!(r.highest_severity(), Warning);
+    }
+}
@@ -1,23 +1,363 @@
-extern crate semcmp;
+extern crate getopts;
+extern crate semverify;
 
-use semcmp::report::{Report, ReportItem};
+use std::env;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use getopts::Options;
+use semverify::json;
+use semverify::manifest::Manifest;
+use semverify::matrix::{self, FeatureSet, Matrix};
+use semverify::report::{Report, ReportItem, Severity};
+use semverify::source::{self, Source};
+use semverify::suppress::{self, Suppressions};
+use semverify::targets::Target;
+use semverify::version::{self, Version};
+
+/// Exit code for command-line usage errors.
+const EXIT_USAGE: i32 = 2;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// The report tree, indented
+    Tree,
+    /// One line per finding, prefixed by the items containing it
+    List,
+    /// The whole report as JSON; see the `json` module
+    Json,
+}
 
 fn main() {
-    let report = semcmp::create_report("inputs/old.rs".as_ref(), "inputs/new.rs".as_ref());
-    print_report(0, &report);
-    println!("Severity: {:?}", report.highest_severity());
+    process::exit(run());
+}
+
+fn run() -> i32 {
+    let args: Vec<String> = env::args().collect();
+    let program = args.first().map_or("semverify", |s| &**s);
+
+    let mut opts = Options::new();
+    opts.optopt("", "min-severity", "only show findings of at least this severity \
+        (debug, note, minor, warning, breaking, major, error; default: note)", "LEVEL");
+    opts.optopt("", "format", "output format (tree, list, json; default: tree)", "FORMAT");
+    opts.optopt("", "current-version", "the version of OLD, for suggesting the next version \
+        (default: read from OLD's Cargo.toml)", "VERSION");
+    opts.optflag("", "verify", "check that the version in NEW's Cargo.toml is bumped enough \
+        for the changes, relative to the version of OLD");
+    opts.optopt("", "git", "compare two revisions of the git repository at REPO, \
+        given as OLD and NEW", "REPO");
+    opts.optopt("", "suppressions", "accept the changes listed in FILE \
+        (default: semverify.toml beside NEW's Cargo.toml, if any)", "FILE");
+    opts.optflag("", "matrix", "compare separately for each combination of target and \
+        feature set, and show the severity of each");
+    opts.optmulti("", "target", "with --matrix, a target triple to compare for \
+        (default: x86_64 Linux, Windows and macOS)", "TRIPLE");
+    opts.optmulti("", "feature-set", "with --matrix, the features to compare with: default, \
+        no-default, all, or a comma-separated list of features, which may include \
+        no-default (default: default, no-default and all)", "SPEC");
+    opts.optopt("p", "package", "the package to compare, within a workspace", "NAME");
+    opts.optflag("q", "quiet", "print nothing, only set the exit code");
+    opts.optflag("h", "help", "print this help");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(e) => return usage_error(program, &opts, &e.to_string()),
+    };
+    if matches.opt_present("help") {
+        print!("{}", usage(program, &opts));
+        return 0
+    }
+
+    let min_severity = match matches.opt_str("min-severity").map(|s| s.parse()) {
+        None => Severity::Note,
+        Some(Ok(severity)) => severity,
+        Some(Err(e)) => return usage_error(program, &opts, &e),
+    };
+    let format = match matches.opt_str("format").as_ref().map(|s| &**s) {
+        None | Some("tree") => Format::Tree,
+        Some("list") => Format::List,
+        Some("json") => Format::Json,
+        Some(other) => return usage_error(program, &opts, &format!("unknown format: {}", other)),
+    };
+    let current_version = match matches.opt_str("current-version").map(|s| Version::parse(&s)) {
+        None => None,
+        Some(Ok(version)) => Some(version),
+        Some(Err(e)) => return usage_error(program, &opts, &format!("invalid version: {}", e)),
+    };
+    // the configurations to compare in, with --matrix
+    let configurations = if matches.opt_present("matrix") {
+        if matches.opt_present("verify") {
+            return usage_error(program, &opts, "--verify cannot be used with --matrix");
+        }
+        let target_triples = matches.opt_strs("target");
+        let target_triples = if target_triples.is_empty() {
+            matrix::DEFAULT_TARGETS.iter().map(|&t| t.to_owned()).collect()
+        } else {
+            target_triples
+        };
+        let mut targets = Vec::new();
+        for triple in &target_triples {
+            match Target::find(triple) {
+                Some(target) => targets.push(target),
+                None => return usage_error(program, &opts, &format!("unknown target: {}", triple)),
+            }
+        }
+        let feature_sets = matches.opt_strs("feature-set");
+        let feature_sets: Vec<_> = if feature_sets.is_empty() {
+            matrix::DEFAULT_FEATURE_SETS.iter().map(|spec| FeatureSet::parse(spec)).collect()
+        } else {
+            feature_sets.iter().map(|spec| FeatureSet::parse(spec)).collect()
+        };
+        Some((targets, feature_sets))
+    } else {
+        None
+    };
+    let (old, new) = match (matches.free.get(0), matches.free.get(1), matches.free.len()) {
+        (Some(old), Some(new), 2) => (old, new),
+        _ => return usage_error(program, &opts, "expected exactly two crates to compare"),
+    };
+
+    let (old, new) = match matches.opt_str("git") {
+        Some(repo) => (
+            Source::Git { repo: PathBuf::from(&repo), rev: old.clone() },
+            Source::Git { repo: PathBuf::from(&repo), rev: new.clone() },
+        ),
+        None => (Source::parse(old), Source::parse(new)),
+    };
+    let package = matches.opt_str("package");
+    let package = package.as_ref().map(|s| &**s);
+    let cargo_home = source::cargo_home();
+    let cargo_home = cargo_home.as_ref().map(|p| &**p);
+    let (old, new) = match (old.checkout(package, cargo_home), new.checkout(package, cargo_home)) {
+        (Ok(old), Ok(new)) => (old, new),
+        (Err(e), _) | (_, Err(e)) => {
+            let _ = writeln!(io::stderr(), "error: {}", e);
+            return exit_code(Severity::Error)
+        }
+    };
+
+    let suppressions = match matches.opt_str("suppressions").map(PathBuf::from).or_else(|| default_suppressions(&new.path)) {
+        None => None,
+        Some(path) => match Suppressions::load(&path) {
+            Ok(suppressions) => Some(suppressions),
+            Err(e) => {
+                let _ = writeln!(io::stderr(), "error: {}", e);
+                return exit_code(Severity::Error)
+            }
+        },
+    };
+
+    if let Some((targets, feature_sets)) = configurations {
+        let (mut report, mut matrix) = semverify::create_matrix(&old.path, &new.path, targets, feature_sets);
+        if let Some(ref suppressions) = suppressions {
+            let cells = matrix.iter_mut().flat_map(|matrix| matrix.reports.iter_mut().flat_map(|row| row.iter_mut()));
+            suppressions.apply_all(Some(&mut report).into_iter().chain(cells));
+        }
+        let severity = std::cmp::max(report.highest_severity(),
+            matrix.as_ref().map_or(Severity::Note, Matrix::highest_severity));
+        let change_severity = std::cmp::max(report.highest_change_severity(),
+            matrix.as_ref().map_or(Severity::Note, Matrix::highest_change_severity));
+        if !matches.opt_present("quiet") {
+            let current_version = current_version.or_else(|| optional_version(&old.path));
+            if format == Format::Json {
+                println!("{}", json::matrix_to_json(&report, matrix.as_ref(), current_version.as_ref()).pretty());
+                return exit_code(severity)
+            }
+            print_findings(&report, format, min_severity);
+            if let Some(ref matrix) = matrix {
+                print_matrix(matrix, format, min_severity);
+            }
+            println!("Severity: {:?}", severity);
+            if let Some(next) = current_version.and_then(|v| version::suggest(&v, change_severity)) {
+                println!("Suggested version: {}", next);
+            }
+        }
+        return exit_code(severity)
+    }
+
+    let mut report = semverify::create_report(&old.path, &new.path);
+    if let Some(ref suppressions) = suppressions {
+        suppressions.apply(&mut report);
+    }
+    let verify = matches.opt_present("verify");
+    let quiet = matches.opt_present("quiet");
+    let current_version = match current_version {
+        Some(version) => Some(version),
+        // with --verify the version is required, so failing to read it is an error
+        None if verify => semverify::crate_version(&mut report, &old.path),
+        None if !quiet => optional_version(&old.path),
+        None => None,
+    };
+    if verify {
+        let new_version = semverify::crate_version(&mut report, &new.path);
+        if let (Some(old_version), Some(new_version)) = (current_version.as_ref(), new_version) {
+            version::verify(&mut report, old_version, &new_version);
+        }
+    }
+    let severity = report.highest_severity();
+    if !quiet {
+        // JSON consumers do their own filtering, so include everything
+        if format == Format::Json {
+            println!("{}", json::report_to_json(&report, current_version.as_ref()).pretty());
+            return exit_code(severity)
+        }
+        print_findings(&report, format, min_severity);
+        println!("Severity: {:?}", severity);
+        if let Some(next) = current_version.and_then(|v| version::suggest(&v, report.highest_change_severity())) {
+            println!("Suggested version: {}", next);
+        }
+    }
+    exit_code(severity)
+}
+
+/// Read the version of a crate where it is only needed to suggest the next
+/// version, so that failing to read it is just a warning.
+fn optional_version(path: &Path) -> Option<Version> {
+    let mut r = Report::new();
+    let version = semverify::crate_version(&mut r, path);
+    for child in &r.children {
+        let _ = writeln!(io::stderr(), "warning: {}", child.item.text);
+    }
+    version
+}
+
+/// Find the suppression file beside a crate's Cargo.toml, if it has one.
+fn default_suppressions(path: &Path) -> Option<PathBuf> {
+    Manifest::find(path)
+        .and_then(|manifest| manifest.parent().map(|dir| dir.join(suppress::FILE_NAME)))
+        .and_then(|file| if file.is_file() { Some(file) } else { None })
+}
+
+fn usage(program: &str, opts: &Options) -> String {
+    let brief = format!("Usage: {} [options] OLD NEW\n\n\
+        Compares the public API of two versions of a crate. OLD and NEW may be\n\
+        crate root files, crate directories, .crate files, or NAME:VERSION for\n\
+        a published crate in the local registry cache. With --git, they are\n\
+        revisions of a repository instead.", program);
+    let exit_codes = "\nExit codes:\n    \
+        0  no changes requiring more than a patch release\n    \
+        1  an error occurred during comparison, or with --verify, the\n       \
+           version was not bumped enough\n    \
+        2  invalid command-line usage\n    \
+        3  the highest severity was minor\n    \
+        4  the highest severity was a warning\n    \
+        5  the highest severity was breaking\n    \
+        6  the highest severity was major\n";
+    format!("{}{}", opts.usage(&brief), exit_codes)
 }
 
-fn print_report(indent: usize, report: &Report) {
+fn usage_error(program: &str, opts: &Options, msg: &str) -> i32 {
+    let _ = writeln!(io::stderr(), "error: {}\n\n{}", msg, usage(program, opts));
+    EXIT_USAGE
+}
+
+/// Map the highest severity in a report to the exit code documented in the
+/// usage text.
+fn exit_code(severity: Severity) -> i32 {
+    match severity {
+        Severity::Debug | Severity::Note => 0,
+        Severity::Error => 1,
+        Severity::Minor => 3,
+        Severity::Warning => 4,
+        Severity::Breaking => 5,
+        Severity::Major => 6,
+    }
+}
+
+/// Print a report as a tree or a list, as chosen by `--format`.
+fn print_findings(report: &Report, format: Format, min_severity: Severity) {
+    match format {
+        Format::List => print_list(&mut Vec::new(), report, min_severity),
+        _ => print_report(0, report, min_severity),
+    }
+}
+
+fn print_report(indent: usize, report: &Report, min_severity: Severity) {
     for child in &report.children {
+        if child.highest_severity() < min_severity { continue }
         print_item(indent, &child.item);
-        print_report(indent + 2, &child);
+        print_report(indent + 2, child, min_severity);
     }
 }
 
 fn print_item(indent: usize, item: &ReportItem) {
     let indent_str = format!("\n{:1$}", "", indent);
-    println!("{}{:?}. {}", &indent_str[1..],
+    println!("{}{:?}. {}{}{}", &indent_str[1..],
         item.severity,
-        item.text.replace("\n", &indent_str));
+        item.text.replace("\n", &indent_str),
+        kind_id(item),
+        locations(item));
+    if let Some(ref suppressed) = item.suppressed {
+        println!("{}  Suppressed {:?}: {}", &indent_str[1..], suppressed.severity, suppressed.reason);
+    }
+}
+
+/// Format the kind of change of an item, e.g. ` [item-removed]`.
+fn kind_id(item: &ReportItem) -> String {
+    item.kind.map_or(String::new(), |kind| format!(" [{}]", kind.id()))
+}
+
+/// Format the source locations of an item, e.g. ` (old.rs:4:1 → new.rs:4:1)`.
+fn locations(item: &ReportItem) -> String {
+    match (&item.old_location, &item.new_location) {
+        (&Some(ref old), &Some(ref new)) => format!(" ({} \u{2192} {})", old, new),
+        (&Some(ref old), &None) => format!(" ({})", old),
+        (&None, &Some(ref new)) => format!(" (\u{2192} {})", new),
+        (&None, &None) => String::new(),
+    }
+}
+
+/// Print the severity in each configuration as a table, followed by the
+/// findings for each configuration.
+fn print_matrix(matrix: &Matrix, format: Format, min_severity: Severity) {
+    let target_width = matrix.targets.iter().map(|t| t.triple.len()).max().unwrap_or(0);
+    let widths: Vec<_> = matrix.feature_sets.iter()
+        .map(|set| std::cmp::max(set.name.len(), "Breaking".len()))
+        .collect();
+    print!("{:1$}", "", target_width);
+    for (set, &width) in matrix.feature_sets.iter().zip(&widths) {
+        print!("  {:1$}", set.name, width);
+    }
+    println!("");
+    for (target, row) in matrix.targets.iter().zip(&matrix.reports) {
+        print!("{:1$}", target.triple, target_width);
+        for (report, &width) in row.iter().zip(&widths) {
+            print!("  {:1$}", format!("{:?}", report.highest_severity()), width);
+        }
+        println!("");
+    }
+
+    for (target, row) in matrix.targets.iter().zip(&matrix.reports) {
+        for (set, report) in matrix.feature_sets.iter().zip(row) {
+            if report.highest_severity() < min_severity { continue }
+            println!("\n{} with features {}: {:?}", target.triple, set.name, report.highest_severity());
+            match format {
+                Format::List => print_list(&mut Vec::new(), report, min_severity),
+                _ => print_report(2, report, min_severity),
+            }
+        }
+    }
+    println!("");
+}
+
+fn print_list<'a>(parents: &mut Vec<&'a str>, report: &'a Report, min_severity: Severity) {
+    for child in &report.children {
+        let item = &child.item;
+        if item.severity >= min_severity && (item.severity != Severity::Note || item.suppressed.is_some()) {
+            let prefix = parents.join(" > ");
+            let mut text = format!("{}{}", item.text.replace("\n", "\n    "), kind_id(item));
+            if let Some(ref suppressed) = item.suppressed {
+                text.push_str(&format!("\n    Suppressed {:?}: {}", suppressed.severity, suppressed.reason));
+            }
+            if prefix.is_empty() {
+                println!("{:?}. {}", item.severity, text);
+            } else {
+                println!("{}: {:?}. {}", prefix, item.severity, text);
+            }
+        }
+        parents.push(&child.item.text);
+        print_list(parents, child, min_severity);
+        parents.pop();
+    }
 }
@@ -0,0 +1,213 @@
+//! Cargo.toml manifests
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use semver::Version;
+use toml;
+
+/// A parsed Cargo.toml.
+pub struct Manifest {
+    pub path: PathBuf,
+    pub table: toml::Table,
+}
+
+impl Manifest {
+    /// Find the manifest belonging to the crate at the given path.
+    ///
+    /// For a directory, this is its Cargo.toml. For a source file, it is the
+    /// nearest Cargo.toml in its ancestors, but only if the file is the root
+    /// of one of that package's targets: a lone file which happens to be
+    /// inside some other package has no manifest.
+    pub fn find(path: &Path) -> Option<PathBuf> {
+        if path.is_dir() {
+            let manifest = path.join("Cargo.toml");
+            return if manifest.is_file() { Some(manifest) } else { None }
+        }
+        let mut dir = path.parent();
+        while let Some(current) = dir {
+            let manifest = current.join("Cargo.toml");
+            if manifest.is_file() {
+                return match Manifest::load(&manifest) {
+                    Ok(package) => {
+                        let file = fs::canonicalize(path).ok();
+                        let is_root = file.is_some() &&
+                            package.target_roots().iter().any(|root| fs::canonicalize(root).ok() == file);
+                        if is_root { Some(manifest) } else { None }
+                    }
+                    // let the caller report the error
+                    Err(_) => Some(manifest),
+                }
+            }
+            dir = current.parent();
+        }
+        None
+    }
+
+    /// Read and parse the manifest at the given path.
+    pub fn load(path: &Path) -> Result<Manifest, String> {
+        let mut text = String::new();
+        if let Err(e) = File::open(path).and_then(|mut f| f.read_to_string(&mut text)) {
+            return Err(format!("Failed to read {}: {}", path.display(), e))
+        }
+        Manifest::parse(path, &text)
+    }
+
+    /// Parse a manifest from its text.
+    pub fn parse(path: &Path, text: &str) -> Result<Manifest, String> {
+        let mut parser = toml::Parser::new(text);
+        match parser.parse() {
+            Some(table) => Ok(Manifest { path: path.to_owned(), table: table }),
+            None => {
+                let errors: Vec<_> = parser.errors.iter().map(|e| e.to_string()).collect();
+                Err(format!("Failed to parse {}: {}", path.display(), errors.join("; ")))
+            }
+        }
+    }
+
+    /// Look up a value by its dotted path, e.g. `package.version`.
+    pub fn lookup(&self, key: &str) -> Option<&toml::Value> {
+        let mut parts = key.split('.');
+        let first = parts.next().and_then(|part| self.table.get(part));
+        parts.fold(first, |value, part| value.and_then(|v| v.as_table()).and_then(|t| t.get(part)))
+    }
+
+    /// The directory containing this manifest.
+    pub fn dir(&self) -> &Path {
+        self.path.parent().unwrap_or_else(|| Path::new("."))
+    }
+
+    /// The `[package] name` of this manifest.
+    pub fn package_name(&self) -> Option<&str> {
+        self.lookup("package.name").and_then(toml::Value::as_str)
+    }
+
+    /// The paths of the members listed in `[workspace] members`, with `*`
+    /// and `?` wildcards expanded to the directories they match.
+    pub fn workspace_members(&self) -> Result<Vec<PathBuf>, String> {
+        let members = self.lookup("workspace.members").and_then(toml::Value::as_slice).unwrap_or(&[]);
+        let mut paths = Vec::new();
+        for member in members.iter().filter_map(toml::Value::as_str) {
+            if member.contains('[') {
+                return Err(format!("In {}: workspace member {:?} uses a character class, which is not supported",
+                    self.path.display(), member))
+            }
+            paths.extend(try!(expand_member(self.dir(), member)));
+        }
+        Ok(paths)
+    }
+
+    /// The root source file of the package's library, or failing that, its
+    /// first binary, following Cargo's defaults.
+    pub fn crate_root(&self) -> Option<PathBuf> {
+        self.target_roots().into_iter().find(|path| path.is_file())
+    }
+
+    /// The root source files the package's library and binaries may have,
+    /// library first, whether or not they exist.
+    fn target_roots(&self) -> Vec<PathBuf> {
+        let dir = self.dir();
+        let lib = self.lookup("lib.path").and_then(toml::Value::as_str).map(|path| dir.join(path));
+        let bins = self.lookup("bin")
+            .and_then(toml::Value::as_slice)
+            .unwrap_or(&[])
+            .iter()
+            .filter_map(|bin| bin.lookup("path"))
+            .filter_map(toml::Value::as_str)
+            .map(|path| dir.join(path));
+        lib.into_iter()
+            .chain(Some(dir.join("src/lib.rs")))
+            .chain(bins)
+            .chain(Some(dir.join("src/main.rs")))
+            .collect()
+    }
+
+    /// The `[package] version` of this manifest.
+    pub fn version(&self) -> Result<Version, String> {
+        match self.lookup("package.version").and_then(toml::Value::as_str) {
+            Some(version) => Version::parse(version)
+                .map_err(|e| format!("Invalid version {:?} in {}: {}", version, self.path.display(), e)),
+            None => Err(format!("No [package] version in {}", self.path.display())),
+        }
+    }
+}
+
+/// Expand the wildcards in a workspace member's path, one component at a
+/// time.
+fn expand_member(dir: &Path, member: &str) -> Result<Vec<PathBuf>, String> {
+    let mut paths = vec![dir.to_owned()];
+    for component in member.split('/') {
+        if !component.contains('*') && !component.contains('?') {
+            for path in &mut paths {
+                path.push(component);
+            }
+            continue
+        }
+        let mut matched = Vec::new();
+        for path in paths {
+            let entries = try!(fs::read_dir(&path)
+                .map_err(|e| format!("Failed to read workspace member directory {}: {}", path.display(), e)));
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                let name = entry.file_name();
+                if name.to_str().map_or(false, |name| wildcard_matches(component, name)) && entry.path().is_dir() {
+                    matched.push(entry.path());
+                }
+            }
+        }
+        matched.sort();
+        paths = matched;
+    }
+    Ok(paths)
+}
+
+/// Whether a file name matches a pattern, in which `*` matches any run of
+/// characters and `?` any single character.
+fn wildcard_matches(pattern: &str, name: &str) -> bool {
+    let mut chars = pattern.chars();
+    match chars.next() {
+        None => name.is_empty(),
+        Some('*') => name.char_indices().map(|(idx, _)| idx).chain(Some(name.len()))
+            .any(|idx| wildcard_matches(chars.as_str(), &name[idx..])),
+        Some(c) => {
+            let mut name_chars = name.chars();
+            match name_chars.next() {
+                Some(n) if c == '?' || c == n => wildcard_matches(chars.as_str(), name_chars.as_str()),
+                _ => false,
+            }
+        }
+    }
+}
+
+#[test]
+fn wildcards() {
+    assert!(wildcard_matches("*", "semverify"));
+    assert!(wildcard_matches("semverify-*", "semverify-cli"));
+    assert!(!wildcard_matches("semverify-*", "semverify"));
+    assert!(wildcard_matches("a?c", "abc"));
+    assert!(!wildcard_matches("a?c", "ac"));
+    assert!(wildcard_matches("*-*", "a-b-c"));
+}
+
+#[test]
+fn manifests_for_files() {
+    use std::io::Write;
+    use tempdir::TempDir;
+
+    let dir = TempDir::new("semverify").unwrap();
+    let write = |name: &str, contents: &str| {
+        let file = dir.path().join(name);
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        File::create(&file).unwrap().write_all(contents.as_bytes()).unwrap();
+        file
+    };
+    let manifest = write("Cargo.toml", "[package]\nname = \"a\"\nversion = \"0.1.0\"\n\n[[bin]]\nname = \"b\"\npath = \"tools/b.rs\"\n");
+    let lib = write("src/lib.rs", "");
+    let bin = write("tools/b.rs", "");
+    let example = write("examples/c.rs", "");
+
+    assert_eq!(Manifest::find(&lib), Some(manifest.clone()));
+    assert_eq!(Manifest::find(&bin), Some(manifest));
+    // not a root of the package's targets, so its features don't apply
+    assert_eq!(Manifest::find(&example), None);
+}
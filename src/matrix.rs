@@ -0,0 +1,248 @@
+//! per-configuration comparison
+//!
+//! Rather than reasoning about every configuration at once, the crates can
+//! be compared separately for each combination of a target and a set of
+//! requested features. Each crate is first reduced to what is compiled in
+//! that configuration, dropping items whose `#[cfg]` does not apply, and the
+//! result compared as usual. This shows changes which are only breaking in
+//! some configurations, such as on one target with one feature.
+
+use std::collections::BTreeSet;
+use std::mem;
+
+use syntax::ast::*;
+use syntax::fold::{self, Folder};
+use syntax::ptr::P;
+use syntax::util::small_vector::SmallVector;
+
+use cfg::Config;
+use compare::compare_crates;
+use features::Features;
+use report::*;
+use targets::Target;
+use ParsedCrate;
+
+/// The targets compared when none are given.
+pub const DEFAULT_TARGETS: &'static [&'static str] = &[
+    "x86_64-unknown-linux-gnu",
+    "x86_64-pc-windows-msvc",
+    "x86_64-apple-darwin",
+];
+
+/// The feature sets compared when none are given.
+pub const DEFAULT_FEATURE_SETS: &'static [&'static str] = &["default", "no-default", "all"];
+
+/// Which features to request, as with Cargo's command-line flags.
+#[derive(Clone, Debug)]
+pub struct FeatureSet {
+    /// How the set was written, for display
+    pub name: String,
+    pub features: Vec<String>,
+    pub default_features: bool,
+    pub all_features: bool,
+}
+
+impl FeatureSet {
+    /// Parse a feature set: `default`, `no-default`, `all`, or a
+    /// comma-separated list of features to request in addition to the
+    /// defaults, which may include `no-default`.
+    pub fn parse(spec: &str) -> FeatureSet {
+        let mut set = FeatureSet {
+            name: spec.to_owned(),
+            features: Vec::new(),
+            default_features: true,
+            all_features: false,
+        };
+        for feature in spec.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            match feature {
+                "default" => {}
+                "no-default" => set.default_features = false,
+                "all" => set.all_features = true,
+                _ => set.features.push(feature.to_owned()),
+            }
+        }
+        set
+    }
+
+    /// The features requested of a crate with the given features.
+    fn requested(&self, features: Option<&Features>) -> BTreeSet<String> {
+        let mut requested: BTreeSet<String> = self.features.iter().cloned().collect();
+        if self.default_features {
+            requested.insert("default".to_owned());
+        }
+        if let (true, Some(features)) = (self.all_features, features) {
+            requested.extend(features.names().into_iter().map(str::to_owned));
+        }
+        requested
+    }
+}
+
+/// The results of comparing in each configuration.
+pub struct Matrix {
+    pub targets: Vec<&'static Target>,
+    pub feature_sets: Vec<FeatureSet>,
+    /// The report for each target, then for each feature set
+    pub reports: Vec<Vec<Report>>,
+}
+
+impl Matrix {
+    /// The highest severity in any configuration.
+    pub fn highest_severity(&self) -> Severity {
+        self.reports.iter()
+            .flat_map(|row| row.iter())
+            .map(Report::highest_severity)
+            .max()
+            .unwrap_or(Severity::Note)
+    }
+
+    /// The highest change severity in any configuration, which determines
+    /// the version bump required.
+    pub fn highest_change_severity(&self) -> Severity {
+        self.reports.iter()
+            .flat_map(|row| row.iter())
+            .map(Report::highest_change_severity)
+            .max()
+            .unwrap_or(Severity::Note)
+    }
+}
+
+/// Compare two crates in each combination of the given targets and feature
+/// sets.
+pub fn compare_matrix(old: &ParsedCrate, new: &ParsedCrate,
+    targets: Vec<&'static Target>, feature_sets: Vec<FeatureSet>) -> Matrix
+{
+    let reports = targets.iter().map(|&target| feature_sets.iter().map(|feature_set| {
+        let mut r = Report::new();
+        let old = configure(&mut r, old, target, feature_set);
+        let new = configure(&mut r, new, target, feature_set);
+        compare_crates(&mut r, &old, &new);
+        r.strip_lazy();
+        r
+    }).collect()).collect();
+    Matrix { targets: targets, feature_sets: feature_sets, reports: reports }
+}
+
+/// Reduce a crate to what is compiled for a target with a feature set.
+fn configure(r: &mut Report, parsed: &ParsedCrate, target: &Target, feature_set: &FeatureSet) -> ParsedCrate {
+    let features = feature_set.requested(parsed.features.as_ref());
+    let mut folder = ConfigureFolder { r: r, target: target, features: &features };
+    let mut krate = parsed.krate.clone();
+    if !folder.configure(&mut krate.attrs) {
+        krate.module.items.clear();
+    }
+    ParsedCrate {
+        krate: folder.fold_crate(krate),
+        codemap: parsed.codemap.clone(),
+        base: parsed.base.clone(),
+        features: parsed.features.clone(),
+    }
+}
+
+struct ConfigureFolder<'a> {
+    r: &'a mut Report,
+    target: &'a Target,
+    features: &'a BTreeSet<String>,
+}
+
+impl<'a> ConfigureFolder<'a> {
+    /// Whether something with the given attributes is compiled, removing its
+    /// `#[cfg]`s if so, as they no longer make a difference.
+    fn configure(&mut self, attrs: &mut Vec<Attribute>) -> bool {
+        if !Config::new(self.r, attrs).applies(self.target, self.features) {
+            return false
+        }
+        attrs.retain(|attr| match attr.node.value.node {
+            MetaItemKind::List(ref name, _) => &**name != "cfg",
+            _ => true,
+        });
+        true
+    }
+
+    /// Remove the members of a list which are not compiled.
+    fn retain<T, F>(&mut self, members: &mut Vec<T>, attrs: F) where F: Fn(&mut T) -> &mut Vec<Attribute> {
+        let all = mem::replace(members, Vec::new());
+        for mut member in all {
+            if self.configure(attrs(&mut member)) {
+                members.push(member);
+            }
+        }
+    }
+
+    fn retain_fields(&mut self, data: &mut VariantData) {
+        match *data {
+            VariantData::Struct(ref mut fields, _) | VariantData::Tuple(ref mut fields, _) =>
+                self.retain(fields, |field| &mut field.attrs),
+            VariantData::Unit(_) => {}
+        }
+    }
+}
+
+impl<'a> Folder for ConfigureFolder<'a> {
+    fn fold_item(&mut self, item: P<Item>) -> SmallVector<P<Item>> {
+        let mut item = item.unwrap();
+        if !self.configure(&mut item.attrs) {
+            return SmallVector::zero()
+        }
+        match item.node {
+            ItemKind::Struct(ref mut data, _) => self.retain_fields(data),
+            ItemKind::Enum(ref mut def, _) => {
+                self.retain(&mut def.variants, |variant| &mut variant.node.attrs);
+                for variant in &mut def.variants {
+                    self.retain_fields(&mut variant.node.data);
+                }
+            }
+            ItemKind::Trait(_, _, _, ref mut items) => self.retain(items, |item| &mut item.attrs),
+            ItemKind::Impl(_, _, _, _, _, ref mut items) => self.retain(items, |item| &mut item.attrs),
+            ItemKind::ForeignMod(ref mut foreign) => self.retain(&mut foreign.items, |item| &mut item.attrs),
+            _ => {}
+        }
+        fold::noop_fold_item(P(item), self)
+    }
+
+    fn fold_mac(&mut self, mac: Mac) -> Mac {
+        fold::noop_fold_mac(mac, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kind::ChangeKind::*;
+    use report::Severity::*;
+    use targets::Target;
+    use tempdir::TempDir;
+    use {create_matrix, findings, write_crate};
+    use super::FeatureSet;
+
+    #[test]
+    fn feature_sets() {
+        let set = FeatureSet::parse("no-default, serde");
+        assert!(!set.default_features && !set.all_features);
+        assert_eq!(set.features, vec!["serde"]);
+        assert!(FeatureSet::parse("all").all_features);
+        assert!(FeatureSet::parse("default").default_features);
+    }
+
+    #[test]
+    fn configurations() {
+        let dir = TempDir::new("semverify").unwrap();
+        let (old, new) = (dir.path().join("old"), dir.path().join("new"));
+        write_crate(&old, &[("src/lib.rs", "#[cfg(unix)] pub fn fd() {} #[cfg(feature = \"extra\")] pub fn extra() {}")]);
+        write_crate(&new, &[("src/lib.rs", "#[cfg(feature = \"extra\")] pub fn extra(_: u8) {}")]);
+
+        let targets = ["x86_64-unknown-linux-gnu", "x86_64-pc-windows-msvc"].iter()
+            .map(|triple| Target::find(triple).unwrap())
+            .collect();
+        let feature_sets = vec![FeatureSet::parse("default"), FeatureSet::parse("extra")];
+        let (r, matrix) = create_matrix(&old, &new, targets, feature_sets);
+        assert!(findings(&r).is_empty());
+
+        // each change only shows up where what it changes is compiled
+        let reports: Vec<Vec<_>> = matrix.unwrap().reports.iter()
+            .map(|row| row.iter().map(findings).collect())
+            .collect();
+        assert_eq!(reports, vec![
+            vec![vec![(ItemRemoved, Major)], vec![(ItemRemoved, Major), (ArgCountChanged, Major)]],
+            vec![vec![], vec![(ArgCountChanged, Major)]],
+        ]);
+    }
+}
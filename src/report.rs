@@ -1,6 +1,11 @@
 //! Reporting data structures
 
 use std::borrow::Cow;
+use std::cmp;
+use std::fmt;
+use std::str::FromStr;
+
+use kind::ChangeKind;
 
 /// Ordered severity levels for report items.
 ///
@@ -23,6 +28,23 @@ pub enum Severity {
     Error,
 }
 
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Severity, String> {
+        match &*s.to_lowercase() {
+            "debug" => Ok(Severity::Debug),
+            "note" => Ok(Severity::Note),
+            "minor" => Ok(Severity::Minor),
+            "warning" => Ok(Severity::Warning),
+            "breaking" => Ok(Severity::Breaking),
+            "major" => Ok(Severity::Major),
+            "error" => Ok(Severity::Error),
+            _ => Err(format!("unknown severity: {}", s)),
+        }
+    }
+}
+
 /// Strictness level of a report item.
 ///
 /// `Lazy` report items are only included in the final report if they have
@@ -43,6 +65,44 @@ pub struct ReportItem {
     pub strict: Strictness,
     pub severity: Severity,
     pub text: Cow<'static, str>,
+    /// What kind of change this is, if it is a finding rather than a note
+    pub kind: Option<ChangeKind>,
+    /// The path of the API item this entry is about, such as `foo::Bar`.
+    ///
+    /// Only set on the entry for the item itself; entries beneath it are
+    /// about the same item.
+    pub path: Option<String>,
+    /// Where the item this entry is about is found in the old crate
+    pub old_location: Option<Location>,
+    /// Where the item this entry is about is found in the new crate
+    pub new_location: Option<Location>,
+    /// Set if this finding was suppressed, in which case its severity has
+    /// been lowered to `Note`
+    pub suppressed: Option<Suppressed>,
+}
+
+/// The record of a finding which was suppressed by configuration.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Suppressed {
+    /// The severity the finding had before it was suppressed
+    pub severity: Severity,
+    /// Why the change is acceptable, as given in the configuration
+    pub reason: String,
+}
+
+/// A position in a source file.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Location {
+    /// The file name, relative to the directory of the crate root
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}:{}:{}", self.file, self.line, self.column)
+    }
 }
 
 /// A node in the tree structure of the report.
@@ -59,21 +119,71 @@ impl Report {
             strict: Strictness::Strict,
             severity: Severity::Note,
             text: "Crate root".into(),
+            kind: None,
+            path: None,
+            old_location: None,
+            new_location: None,
+            suppressed: None,
         })
     }
 
-    /// Calculate the highest severity item contained in this report.
+    /// Calculate the highest severity item contained in this report,
+    /// including this entry itself.
+    ///
+    /// A finding's own severity counts even when it has children, as these
+    /// are usually notes explaining it, which must not hide the finding. A
+    /// grouping entry, which is a `Note`, takes the severity of its children.
     pub fn highest_severity(&self) -> Severity {
-        self.children.iter().map(Report::highest_severity).max().unwrap_or(self.item.severity)
+        self.children.iter().map(Report::highest_severity).fold(self.item.severity, cmp::max)
+    }
+
+    /// Calculate the highest severity of a change to the API in this report,
+    /// which determines the version bump required.
+    ///
+    /// Tool notes and warnings are not changes, so they are ignored; `Error`
+    /// is kept, as then the comparison could not be made.
+    pub fn highest_change_severity(&self) -> Severity {
+        let own = match self.item.severity {
+            Severity::Debug | Severity::Note | Severity::Warning => Severity::Note,
+            severity => severity,
+        };
+        self.children.iter().map(Report::highest_change_severity).fold(own, cmp::max)
     }
 
     /// Strip lazy entries. Returns whether this entry might be deleted.
     pub fn strip_lazy(&mut self) -> bool {
         // delete all Lazy children
         delete_if(&mut self.children, Report::strip_lazy);
-        // delete us if we are Lazy and no children are Strict
+        // delete us if we are Lazy and no children are Strict, or Lazy with
+        // Strict descendants of their own
         self.item.strict == Strictness::Lazy &&
-            self.children.iter().all(|c| c.item.strict < Strictness::Strict)
+            self.children.iter().all(|c| c.item.strict == Strictness::Inherit)
+    }
+
+    /// Raise every Minor or Breaking change in this report to Major.
+    ///
+    /// Used where the RFC considers all changes Major, such as the
+    /// signatures of trait items.
+    pub fn escalate_changes(&mut self) {
+        for child in &mut self.children {
+            if child.item.severity == Severity::Minor || child.item.severity == Severity::Breaking {
+                child.item.severity = Severity::Major;
+            }
+            child.escalate_changes();
+        }
+    }
+
+    /// Set the path of the API item this entry is about.
+    pub fn with_path(&mut self, path: String) -> &mut Report {
+        self.item.path = Some(path);
+        self
+    }
+
+    /// Set where the item this entry is about is found in each crate.
+    pub fn with_locations(&mut self, old: Option<Location>, new: Option<Location>) -> &mut Report {
+        self.item.old_location = old;
+        self.item.new_location = new;
+        self
     }
 
     /// Insert a new item into this report, returning it.
@@ -83,6 +193,12 @@ impl Report {
     }
 }
 
+impl Default for Report {
+    fn default() -> Report {
+        Report::new()
+    }
+}
+
 impl From<ReportItem> for Report {
     fn from(val: ReportItem) -> Report {
         Report { item: val, children: Vec::new() }
@@ -92,31 +208,56 @@ impl From<ReportItem> for Report {
 macro_rules! push {
     (@_format $string:expr) => ($string.into());
     (@_format $string:expr, $($rest:tt)*) => (format!($string, $($rest)*).into());
-    ($report:expr, $severity:ident, $($rest:tt)*) => {
+    (@_item $report:expr, $strict:ident, $severity:ident, $kind:expr, $text:expr) => {
         $report.push(::report::ReportItem {
-            strict: ::report::Strictness::Strict,
+            strict: ::report::Strictness::$strict,
             severity: ::report::Severity::$severity,
-            text: push!(@_format $($rest)*),
+            text: $text,
+            kind: $kind,
+            path: None,
+            old_location: None,
+            new_location: None,
+            suppressed: None,
         })
     };
+    // Findings: the text defaults to the description of the kind of change
+    ($report:expr, $severity:ident($kind:ident)) => {
+        push!(@_item $report, Strict, $severity, Some(::kind::ChangeKind::$kind),
+            ::kind::ChangeKind::$kind.description().into())
+    };
+    ($report:expr, $severity:ident($kind:ident), $($rest:tt)*) => {
+        push!(@_item $report, Strict, $severity, Some(::kind::ChangeKind::$kind),
+            push!(@_format $($rest)*))
+    };
+    // Findings whose kind is chosen at runtime
+    ($report:expr, $severity:ident[$kind:expr], $($rest:tt)*) => {
+        push!(@_item $report, Strict, $severity, Some($kind), push!(@_format $($rest)*))
+    };
+    // Tool messages and structure
+    ($report:expr, $severity:ident, $($rest:tt)*) => {
+        push!(@_item $report, Strict, $severity, None, push!(@_format $($rest)*))
+    };
     ($report:expr, $strict:ident $severity:ident, $($rest:tt)*) => {
-        $report.push(::report::ReportItem {
-            strict: ::report::Strictness::$strict,
-            severity: ::report::Severity::$severity,
-            text: push!(@_format $($rest)*),
-        })
+        push!(@_item $report, $strict, $severity, None, push!(@_format $($rest)*))
     };
 }
 
 macro_rules! changed {
+    ($report:expr, $severity:ident($kind:ident), $what:expr, ($was:expr => $now:expr) $($rest:tt)*) => {
+        push!($report, $severity($kind),
+            concat!($what, " has changed:\n  Was: {:?}\n  Now: {:?}")
+            $($rest)*, $was, $now
+        )
+    };
     ($report:expr, $severity:ident, $what:expr, ($was:expr => $now:expr) $($rest:tt)*) => {
         push!($report, $severity,
             concat!($what, " has changed:\n  Was: {:?}\n  Now: {:?}")
             $($rest)*, $was, $now
         )
-    }
+    };
 }
 
+#[allow(unknown_lints, needless_range_loop)]
 fn delete_if<T, F>(vec: &mut Vec<T>, mut f: F)
     where F: FnMut(&mut T) -> bool
 {
@@ -137,3 +278,55 @@ fn delete_if<T, F>(vec: &mut Vec<T>, mut f: F)
         vec.truncate(len - del);
     }
 }
+
+#[test]
+fn nested_lazy_entries() {
+    let mut r = Report::new();
+    {
+        // a finding two Lazy levels down keeps both
+        let impls = push!(r, Lazy Note, "trait impls");
+        let added = push!(impls, Lazy Note, "impl Clone for S (derived)");
+        push!(added, Breaking(ImplAdded));
+    }
+    {
+        let impls = &mut r.children[0];
+        let unchanged = push!(impls, Lazy Note, "impl Copy for S");
+        push!(unchanged, Inherit Note, "#[cfg(unix)]");
+    }
+    {
+        // while Inherit children alone do not
+        let unchanged = push!(r, Lazy Note, "inherent impls");
+        push!(unchanged, Inherit Note, "#[cfg(unix)]");
+    }
+    r.strip_lazy();
+    assert_eq!(r.children.len(), 1);
+    assert_eq!(r.children[0].children.len(), 1);
+    assert_eq!(r.children[0].children[0].item.text, "impl Clone for S (derived)");
+}
+
+#[test]
+fn severity_of_findings_with_notes() {
+    let mut r = Report::new();
+    {
+        let group = push!(r, Note, "fn foo");
+        let arg = push!(group, Major(ArgGeneralized));
+        push!(arg, Note, "u8 may not satisfy the bounds");
+    }
+    assert_eq!(r.children[0].children[0].highest_severity(), Severity::Major);
+    assert_eq!(r.children[0].highest_severity(), Severity::Major);
+    assert_eq!(r.highest_severity(), Severity::Major);
+    assert_eq!(r.highest_change_severity(), Severity::Major);
+}
+
+#[test]
+fn severity_of_groups() {
+    let mut r = Report::new();
+    {
+        let group = push!(r, Note, "fn foo");
+        push!(group, Debug, "checked");
+    }
+    push!(r, Warning, "Could not parse macro arms");
+    assert_eq!(r.children[0].highest_severity(), Severity::Note);
+    assert_eq!(r.highest_severity(), Severity::Warning);
+    assert_eq!(r.highest_change_severity(), Severity::Note);
+}
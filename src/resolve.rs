@@ -0,0 +1,383 @@
+//! module namespaces and re-exports
+//!
+//! The public API of a module is not just the items defined in it, but also
+//! every name brought in by a `pub use`. Paths in `use` items are resolved
+//! against the crate root (or the current module, for `self::` and `super::`)
+//! the same way as the compiler would, minus the parts which require knowing
+//! about other crates: anything which does not resolve to a local item is
+//! treated as a re-export from another crate and compared by path.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Deref;
+
+use syntax::ast::*;
+
+use report::*;
+use cfg::Config;
+use compare::{Member, is_public};
+
+/// A module, identified by the path at which it is defined.
+pub struct Module<'a> {
+    pub path: Vec<Name>,
+    pub module: &'a Mod,
+    /// Items whose #[cfg] flags also apply to the module, such as the `mod`
+    /// items and `use` items on the way to it
+    pub via: Vec<&'a Item>,
+}
+
+/// A name in a module's namespace.
+#[derive(Clone)]
+pub struct Entry<'a> {
+    /// The name under which the item is visible
+    pub name: Name,
+    /// The item itself, or the `use` item for re-exports from other crates
+    pub item: &'a Item,
+    /// The path of the module defining `item`
+    pub module: Vec<Name>,
+    /// Items whose #[cfg] flags also apply to this entry
+    pub via: Vec<&'a Item>,
+    /// The path of a re-export from another crate
+    pub external: Option<String>,
+}
+
+impl<'a> Entry<'a> {
+    /// The path at which this entry is defined, if it is a module.
+    pub fn child_path(&self) -> Vec<Name> {
+        let mut path = self.module.clone();
+        path.push(self.item.ident.name);
+        path
+    }
+}
+
+impl<'a> Deref for Entry<'a> {
+    type Target = Item;
+    fn deref(&self) -> &Item { self.item }
+}
+
+impl<'a> Member for Entry<'a> {
+    fn name(&self) -> Name { self.name }
+    fn config(&self, r: &mut Report) -> Config {
+        let mut configs: Vec<_> = self.via.iter().map(|item| Config::new(r, &item.attrs)).collect();
+        configs.push(Config::new(r, &self.item.attrs));
+        let mut config = Config::All(configs);
+        config.simplify();
+        config
+    }
+    fn is_public(&self) -> bool { is_public(self.item) }
+}
+
+/// Resolves the namespaces of modules in a crate.
+pub struct Namespaces<'a> {
+    krate: &'a Crate,
+    /// The modules whose namespaces are currently being resolved, so that
+    /// cycles (e.g. through glob imports) are cut short, and whether what
+    /// has been found for each depends on a cycle which was cut
+    resolving: RefCell<Vec<(*const Mod, bool)>>,
+    /// The namespaces resolved so far
+    resolved: RefCell<HashMap<*const Mod, Vec<Entry<'a>>>>,
+}
+
+impl<'a> Namespaces<'a> {
+    pub fn new(krate: &'a Crate) -> Namespaces<'a> {
+        Namespaces { krate: krate, resolving: RefCell::new(Vec::new()), resolved: RefCell::new(HashMap::new()) }
+    }
+
+    /// The crate root module.
+    pub fn root(&self) -> Module<'a> {
+        Module { path: Vec::new(), module: &self.krate.module, via: Vec::new() }
+    }
+
+    /// Every name in the given module: the items defined in it, followed by
+    /// the names it re-exports with `pub use`.
+    pub fn entries(&self, module: &Module<'a>) -> Vec<Entry<'a>> {
+        let key = module.module as *const Mod;
+        if let Some(entries) = self.resolved.borrow().get(&key) {
+            return entries.clone()
+        }
+
+        // A module which is already being resolved is part of a cycle. It
+        // is resolved again, except that the imports which lead back into
+        // the cycle only see the items defined in the modules they name.
+        if self.resolving.borrow().iter().any(|&(m, _)| m == key) {
+            return self.resolve(module, true)
+        }
+        self.resolving.borrow_mut().push((key, false));
+        let entries = self.resolve(module, false);
+        let (_, cut) = self.resolving.borrow_mut().pop().unwrap();
+        if !cut {
+            self.resolved.borrow_mut().insert(key, entries.clone());
+        }
+        entries
+    }
+
+    /// Work out the namespace of a module. If `cut`, imports from modules
+    /// which are being resolved only see the items defined there.
+    fn resolve(&self, module: &Module<'a>, cut: bool) -> Vec<Entry<'a>> {
+        use syntax::ast::ItemKind::Use;
+
+        let mut entries = defined_entries(module);
+        let mut globs = Vec::new();
+        for item in &module.module.items {
+            let vp = match item.node {
+                // Private imports are not part of the namespace seen by others
+                Use(ref vp) if item.vis == Visibility::Public => vp,
+                _ => continue,
+            };
+            match vp.node {
+                ViewPath_::ViewPathSimple(ident, ref path) => {
+                    let segments: Vec<_> = path.segments.iter().map(|s| s.identifier.name).collect();
+                    self.import(&mut entries, item, module, ident.name, &segments, cut);
+                }
+                ViewPath_::ViewPathList(ref path, ref list) => {
+                    let prefix: Vec<_> = path.segments.iter().map(|s| s.identifier.name).collect();
+                    for list_item in list {
+                        match list_item.node {
+                            PathListItemKind::Ident { name, rename, .. } => {
+                                let mut segments = prefix.clone();
+                                segments.push(name.name);
+                                let name = rename.unwrap_or(name).name;
+                                self.import(&mut entries, item, module, name, &segments, cut);
+                            }
+                            PathListItemKind::Mod { rename, .. } => {
+                                let name = match (rename, prefix.last()) {
+                                    (Some(rename), _) => rename.name,
+                                    (None, Some(&last)) => last,
+                                    (None, None) => continue,
+                                };
+                                self.import(&mut entries, item, module, name, &prefix, cut);
+                            }
+                        }
+                    }
+                }
+                ViewPath_::ViewPathGlob(ref path) => globs.push((item, path)),
+            }
+        }
+
+        // Glob imports are shadowed by any explicitly defined name
+        let explicit: Vec<_> = entries.iter().map(|e| e.name).collect();
+        for (item, path) in globs {
+            let segments: Vec<_> = path.segments.iter().map(|s| s.identifier.name).collect();
+            for target in self.resolve_module(module, &segments, cut) {
+                for entry in self.lookup(&target, cut) {
+                    if is_named(&entry) && entry.is_public() && !explicit.contains(&entry.name) {
+                        entries.push(reexport(item, &target, entry));
+                    }
+                }
+            }
+        }
+        entries
+    }
+
+    /// The namespace of a module as seen by an import. If `cut` and the
+    /// module is being resolved, the import closes a cycle, so it only sees
+    /// the items defined there.
+    fn lookup(&self, module: &Module<'a>, cut: bool) -> Vec<Entry<'a>> {
+        let key = module.module as *const Mod;
+        let position = self.resolving.borrow().iter().position(|&(m, _)| m == key);
+        match position {
+            Some(position) if cut => {
+                // Everything resolved since depends on the cycle being cut
+                for frame in &mut self.resolving.borrow_mut()[position + 1..] {
+                    frame.1 = true;
+                }
+                defined_entries(module)
+            }
+            _ => self.entries(module),
+        }
+    }
+
+    /// Add the entries named by `segments` to the namespace under `name`.
+    fn import(&self, entries: &mut Vec<Entry<'a>>, item: &'a Item, module: &Module<'a>, name: Name, segments: &[Name], cut: bool) {
+        let mut found = false;
+        if let Some((&last, prefix)) = segments.split_last() {
+            for target in self.resolve_module(module, prefix, cut) {
+                for entry in self.lookup(&target, cut) {
+                    if is_named(&entry) && entry.name == last {
+                        found = true;
+                        let mut entry = reexport(item, &target, entry);
+                        entry.name = name;
+                        entries.push(entry);
+                    }
+                }
+            }
+        }
+        if !found {
+            entries.push(Entry {
+                name: name,
+                item: item,
+                module: module.path.clone(),
+                via: Vec::new(),
+                external: Some(segments.iter().map(|s| s.to_string()).collect::<Vec<_>>().join("::")),
+            });
+        }
+    }
+
+    /// Find the modules a `use` path prefix refers to, relative to `module`.
+    ///
+    /// There may be several if a module is defined more than once under
+    /// different #[cfg] flags. Returns nothing if the path leaves the crate.
+    fn resolve_module(&self, module: &Module<'a>, segments: &[Name], cut: bool) -> Vec<Module<'a>> {
+        let is_relative = |name: &Name| {
+            let name = name.as_str();
+            &*name == "self" || &*name == "super"
+        };
+
+        let mut rest = segments;
+        let mut current = if segments.first().map_or(false, &is_relative) {
+            let mut path = module.path.clone();
+            while let Some((first, tail)) = rest.split_first() {
+                if !is_relative(first) { break }
+                if &*first.as_str() == "super" {
+                    path.pop();
+                }
+                rest = tail;
+            }
+            match self.module_at(&path) {
+                Some(found) => vec![found],
+                None => return Vec::new(),
+            }
+        } else {
+            vec![self.root()]
+        };
+
+        for &segment in rest {
+            let mut next = Vec::new();
+            for parent in &current {
+                for entry in self.lookup(parent, cut) {
+                    if entry.name != segment { continue }
+                    if let ItemKind::Mod(ref inner) = entry.item.node {
+                        let mut via = parent.via.clone();
+                        via.extend(entry.via.iter().cloned());
+                        via.push(entry.item);
+                        next.push(Module { path: entry.child_path(), module: inner, via: via });
+                    }
+                }
+            }
+            current = next;
+        }
+        current
+    }
+
+    /// Find the module defined at the given path, without following
+    /// re-exports.
+    fn module_at(&self, path: &[Name]) -> Option<Module<'a>> {
+        let mut module = self.root();
+        for &segment in path {
+            let item = match module.module.items.iter().find(|item| item.ident.name == segment && match item.node {
+                ItemKind::Mod(_) => true, _ => false,
+            }) {
+                Some(item) => item,
+                None => return None,
+            };
+            if let ItemKind::Mod(ref inner) = item.node {
+                module.path.push(segment);
+                module.module = inner;
+            }
+        }
+        Some(module)
+    }
+}
+
+/// The items defined in a module, without its imports.
+fn defined_entries<'a>(module: &Module<'a>) -> Vec<Entry<'a>> {
+    module.module.items.iter()
+        .filter(|item| match item.node { ItemKind::Use(..) => false, _ => true })
+        .map(|item| Entry {
+            name: item.ident.name,
+            item: item,
+            module: module.path.clone(),
+            via: Vec::new(),
+            external: None,
+        })
+        .collect()
+}
+
+/// Make an entry found in `target` into one re-exported by the `use` item.
+fn reexport<'a>(item: &'a Item, target: &Module<'a>, mut entry: Entry<'a>) -> Entry<'a> {
+    let mut via = vec![item];
+    via.extend(target.via.iter().cloned());
+    via.extend(entry.via.drain(..));
+    entry.via = via;
+    entry
+}
+
+/// Whether an item can be referred to by name, unlike e.g. impls.
+fn is_named(item: &Item) -> bool {
+    use syntax::ast::ItemKind::*;
+    match item.node {
+        Impl(..) | DefaultImpl(..) | ForeignMod(..) | Mac(..) => false,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kind::ChangeKind::*;
+    use report::Severity::*;
+    use {compare_files, findings};
+
+    #[test]
+    fn moved_items() {
+        // still visible at its old path through the re-export
+        let r = compare_files(
+            &[("src/lib.rs", "pub struct Foo(pub u8);")],
+            &[("src/lib.rs", "mod imp; pub use imp::Foo;"), ("src/imp.rs", "pub struct Foo(pub u8);")]);
+        assert_eq!(findings(&r), vec![]);
+
+        // without it, the item is gone
+        let r = compare_files(
+            &[("src/lib.rs", "mod imp; pub use imp::Foo;"), ("src/imp.rs", "pub struct Foo(pub u8);")],
+            &[("src/lib.rs", "mod imp;"), ("src/imp.rs", "pub struct Foo(pub u8);")]);
+        assert_eq!(findings(&r), vec![(ItemRemoved, Major)]);
+
+        // including re-exports from other crates
+        let r = compare_files(
+            &[("src/lib.rs", "pub use std::fmt::Write;")],
+            &[("src/lib.rs", "")]);
+        assert_eq!(findings(&r), vec![(ItemRemoved, Major)]);
+    }
+
+    #[test]
+    fn glob_imports() {
+        // the glob's `S` is shadowed by the one defined here, so changing it
+        // makes no difference
+        let r = compare_files(
+            &[("src/lib.rs", "mod a { pub struct S(pub u8); } pub use a::*; pub struct S(pub u16);")],
+            &[("src/lib.rs", "mod a { pub struct S(pub u32); } pub use a::*; pub struct S(pub u16);")]);
+        assert_eq!(findings(&r), vec![]);
+    }
+
+    #[test]
+    fn relative_paths() {
+        let r = compare_files(
+            &[("src/lib.rs", "pub mod a { pub struct S; } pub mod b { pub use super::a::S; }")],
+            &[("src/lib.rs", "pub mod a { mod imp { pub struct S; } pub use self::imp::S; } \
+                              pub mod b { pub use super::a::S; }")]);
+        assert_eq!(findings(&r), vec![]);
+    }
+
+    #[test]
+    fn renamed_imports() {
+        let imp = ("src/imp.rs", "pub struct A; pub struct B;");
+        let r = compare_files(
+            &[("src/lib.rs", "pub struct A; pub struct C;")],
+            &[("src/lib.rs", "mod imp; pub use imp::{A, B as C};"), imp]);
+        assert_eq!(findings(&r), vec![]);
+
+        // the items are only visible under their new names
+        let r = compare_files(
+            &[("src/lib.rs", "mod imp; pub use imp::{A, B as C};"), imp],
+            &[("src/lib.rs", "mod imp; pub use imp::{A, B};"), imp]);
+        assert_eq!(findings(&r), vec![(ItemRemoved, Major), (ItemAdded, Minor)]);
+    }
+
+    #[test]
+    fn glob_cycles() {
+        // `a` and the crate root each see everything in the other
+        let r = compare_files(
+            &[("src/lib.rs", "pub mod a { pub use super::*; pub struct A; } pub use a::*; pub struct R;")],
+            &[("src/lib.rs", "pub mod a { pub use super::*; pub struct A; } pub use a::*;")]);
+        assert_eq!(findings(&r), vec![(ItemRemoved, Major), (ItemRemoved, Major)]);
+    }
+}
@@ -0,0 +1,135 @@
+//! a small SAT solver
+//!
+//! Questions about `#[cfg]` coverage, such as whether one config is a subset
+//! of another, are boolean satisfiability problems. Enumerating assignments
+//! is exponential in the number of features and flags involved, so formulas
+//! are handed to a DPLL solver instead. Formulas from real crates are small
+//! and easy, so unit propagation and naive branching are enough.
+
+/// A variable or its negation.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Lit {
+    var: usize,
+    positive: bool,
+}
+
+impl Lit {
+    /// The opposite of this literal.
+    pub fn negate(self) -> Lit {
+        Lit { var: self.var, positive: !self.positive }
+    }
+}
+
+/// A formula in conjunctive normal form.
+pub struct Formula {
+    vars: usize,
+    clauses: Vec<Vec<Lit>>,
+}
+
+impl Formula {
+    /// Construct a new formula with no variables, which is satisfiable.
+    pub fn new() -> Formula {
+        Formula { vars: 0, clauses: Vec::new() }
+    }
+
+    /// Allocate a new variable, returning its positive literal.
+    pub fn new_var(&mut self) -> Lit {
+        self.vars += 1;
+        Lit { var: self.vars - 1, positive: true }
+    }
+
+    /// Require that at least one of the literals is true.
+    pub fn add_clause(&mut self, clause: Vec<Lit>) {
+        self.clauses.push(clause);
+    }
+
+    /// Determine whether any assignment of the variables satisfies every
+    /// clause.
+    pub fn satisfiable(&self) -> bool {
+        let mut assignment = vec![None; self.vars];
+        solve(&self.clauses, &mut assignment)
+    }
+}
+
+fn value(assignment: &[Option<bool>], lit: Lit) -> Option<bool> {
+    assignment[lit.var].map(|value| value == lit.positive)
+}
+
+fn solve(clauses: &[Vec<Lit>], assignment: &mut Vec<Option<bool>>) -> bool {
+    let mut trail = Vec::new();
+    if !propagate(clauses, assignment, &mut trail) {
+        undo(assignment, &trail);
+        return false
+    }
+
+    // branch on the first literal of an unsatisfied clause
+    let branch = clauses.iter()
+        .filter(|clause| !clause.iter().any(|&lit| value(assignment, lit) == Some(true)))
+        .flat_map(|clause| clause.iter())
+        .find(|&&lit| value(assignment, lit).is_none())
+        .cloned();
+    let lit = match branch {
+        Some(lit) => lit,
+        // every clause is satisfied
+        None => return true,
+    };
+    for &choice in &[lit, lit.negate()] {
+        assignment[choice.var] = Some(choice.positive);
+        if solve(clauses, assignment) {
+            return true
+        }
+        assignment[choice.var] = None;
+    }
+    undo(assignment, &trail);
+    false
+}
+
+/// Assign every literal which is the last unassigned one in a clause.
+/// Returns `false` if a clause cannot be satisfied.
+fn propagate(clauses: &[Vec<Lit>], assignment: &mut Vec<Option<bool>>, trail: &mut Vec<usize>) -> bool {
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for clause in clauses {
+            let mut unassigned = None;
+            let mut count = 0;
+            let mut satisfied = false;
+            for &lit in clause {
+                match value(assignment, lit) {
+                    Some(true) => { satisfied = true; break }
+                    Some(false) => {}
+                    None => { count += 1; unassigned = Some(lit); }
+                }
+            }
+            if satisfied { continue }
+            match (count, unassigned) {
+                (0, _) => return false,
+                (1, Some(lit)) => {
+                    assignment[lit.var] = Some(lit.positive);
+                    trail.push(lit.var);
+                    changed = true;
+                }
+                _ => {}
+            }
+        }
+    }
+    true
+}
+
+fn undo(assignment: &mut Vec<Option<bool>>, trail: &[usize]) {
+    for &var in trail {
+        assignment[var] = None;
+    }
+}
+
+#[test]
+fn small_formulas() {
+    let mut formula = Formula::new();
+    let a = formula.new_var();
+    let b = formula.new_var();
+    formula.add_clause(vec![a, b]);
+    formula.add_clause(vec![a.negate(), b]);
+    assert!(formula.satisfiable());
+    formula.add_clause(vec![b.negate()]);
+    assert!(!formula.satisfiable());
+}
@@ -0,0 +1,284 @@
+//! crate sources
+//!
+//! Crates can be compared from places other than a plain directory, such as
+//! a revision in a git repository or a published `.crate` file. These are
+//! materialized into temporary directories using only local tools, so that
+//! the rest of the comparison can treat every crate as a path on disk.
+
+use std::env;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use flate2::read::GzDecoder;
+use semver::Version;
+use tar::Archive;
+use tempdir::TempDir;
+
+use manifest::Manifest;
+
+/// Where to read a crate from.
+pub enum Source {
+    /// A crate root file or package directory
+    Path(PathBuf),
+    /// A revision (commit, tag or branch) in a local git repository
+    Git { repo: PathBuf, rev: String },
+    /// A `.crate` file, as published to a registry
+    Tarball(PathBuf),
+    /// A published version of a crate, from Cargo's local registry cache
+    Registry { name: String, version: Version },
+}
+
+/// The sources of a crate on disk.
+pub struct Checkout {
+    /// The crate root file or package directory
+    pub path: PathBuf,
+    /// The temporary directory holding the sources, deleted on drop
+    temp: Option<TempDir>,
+}
+
+impl Source {
+    /// Interpret a command-line argument naming a crate.
+    ///
+    /// Existing paths are used as they are, or extracted if they are `.crate`
+    /// files. Otherwise, `NAME:VERSION` refers to a published crate in the
+    /// local registry cache.
+    pub fn parse(arg: &str) -> Source {
+        let path = Path::new(arg);
+        if path.exists() {
+            return if path.is_file() && path.extension().map_or(false, |ext| ext == "crate") {
+                Source::Tarball(path.to_owned())
+            } else {
+                Source::Path(path.to_owned())
+            }
+        }
+        let mut parts = arg.splitn(2, ':');
+        if let (Some(name), Some(version)) = (parts.next(), parts.next()) {
+            if let Ok(version) = Version::parse(version) {
+                return Source::Registry { name: name.to_owned(), version: version }
+            }
+        }
+        Source::Path(path.to_owned())
+    }
+
+    /// Make the sources available on disk.
+    ///
+    /// If a package name is given, it selects a member of a workspace.
+    /// Published crates are looked up in the registry cache of the given
+    /// Cargo home directory, usually that from `cargo_home()`.
+    pub fn checkout(&self, package: Option<&str>, cargo_home: Option<&Path>) -> Result<Checkout, String> {
+        match *self {
+            Source::Path(ref path) => Ok(Checkout {
+                path: try!(select_package(path, package)),
+                temp: None,
+            }),
+            Source::Git { ref repo, ref rev } => {
+                let temp = try!(TempDir::new("semverify")
+                    .map_err(|e| format!("Failed to create temporary directory: {}", e)));
+                let path = try!(git_archive(repo, rev, temp.path()));
+                Ok(Checkout {
+                    path: try!(select_package(&path, package)),
+                    temp: Some(temp),
+                })
+            }
+            Source::Tarball(ref path) => extract_crate(path),
+            Source::Registry { ref name, ref version } => {
+                let dir_name = format!("{}-{}", name, version);
+                // Prefer the sources Cargo has already unpacked
+                if let Some(dir) = cargo_home.and_then(|home| find_in_registry(home, "src", &dir_name)) {
+                    return Ok(Checkout { path: dir, temp: None })
+                }
+                match cargo_home.and_then(|home| find_in_registry(home, "cache", &format!("{}.crate", dir_name))) {
+                    Some(file) => extract_crate(&file),
+                    None => Err(format!("{} {} is not in the local registry cache", name, version)),
+                }
+            }
+        }
+    }
+}
+
+/// Extract a `.crate` file into a temporary directory.
+fn extract_crate(path: &Path) -> Result<Checkout, String> {
+    let temp = try!(TempDir::new("semverify")
+        .map_err(|e| format!("Failed to create temporary directory: {}", e)));
+    try!(File::open(path)
+        .and_then(GzDecoder::new)
+        .and_then(|decoder| Archive::new(decoder).unpack(temp.path()))
+        .map_err(|e| format!("Failed to extract {}: {}", path.display(), e)));
+
+    // The package is in a NAME-VERSION directory at the top of the archive
+    let mut dirs = try!(fs::read_dir(temp.path())
+        .map_err(|e| format!("Failed to extract {}: {}", path.display(), e)))
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir());
+    match (dirs.next(), dirs.next()) {
+        (Some(dir), None) => Ok(Checkout { path: dir, temp: Some(temp) }),
+        _ => Err(format!("{} does not contain a single package directory", path.display())),
+    }
+}
+
+/// Cargo's home directory, which holds the local registry cache: `CARGO_HOME`
+/// if it is set, or `~/.cargo`.
+pub fn cargo_home() -> Option<PathBuf> {
+    env::var_os("CARGO_HOME").map(PathBuf::from).or_else(|| env::home_dir().map(|home| home.join(".cargo")))
+}
+
+/// Find an entry of the given name in any of the local registry's
+/// per-index directories, e.g. `~/.cargo/registry/src/*/NAME`.
+fn find_in_registry(cargo_home: &Path, kind: &str, name: &str) -> Option<PathBuf> {
+    let indexes = match fs::read_dir(cargo_home.join("registry").join(kind)) {
+        Ok(indexes) => indexes,
+        Err(_) => return None,
+    };
+    indexes.filter_map(|entry| entry.ok())
+        .map(|entry| entry.path().join(name))
+        .find(|path| path.exists())
+}
+
+/// Extract a revision of the git repository containing `path` into `dest`,
+/// returning the location of `path` within the extracted tree.
+///
+/// The whole work tree is extracted, as a crate may refer to files outside
+/// its own directory, such as through `#[path]` or a workspace manifest.
+fn git_archive(path: &Path, rev: &str, dest: &Path) -> Result<PathBuf, String> {
+    let top_level = try!(git(path, &["rev-parse", "--show-toplevel"]));
+    let prefix = try!(git(path, &["rev-parse", "--show-prefix"]));
+
+    // git's errors go straight to our stderr: only stdout is read, so if
+    // they were piped and filled the pipe, git and we would wait on each other
+    let mut child = try!(Command::new("git")
+        .current_dir(top_level.trim())
+        .args(&["archive", "--format=tar", rev])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|e| format!("Failed to run git: {}", e)));
+    let unpacked = Archive::new(child.stdout.take().unwrap()).unpack(dest);
+    let status = try!(child.wait().map_err(|e| format!("Failed to run git: {}", e)));
+    if !status.success() {
+        return Err(format!("git archive {} failed", rev))
+    }
+    try!(unpacked.map_err(|e| format!("Failed to extract {}: {}", rev, e)));
+    Ok(dest.join(prefix.trim()))
+}
+
+/// Run a git command in `dir`, returning its output.
+fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = try!(Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e)));
+    if !output.status.success() {
+        return Err(format!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()))
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Find the directory of the named package, which is either the package at
+/// `path` or a member of the workspace there.
+fn select_package(path: &Path, package: Option<&str>) -> Result<PathBuf, String> {
+    let name = match package {
+        Some(name) => name,
+        None => return Ok(path.to_owned()),
+    };
+    let manifest = try!(Manifest::load(&path.join("Cargo.toml")));
+    if manifest.package_name() == Some(name) {
+        return Ok(path.to_owned())
+    }
+    for member in try!(manifest.workspace_members()) {
+        let member_manifest = try!(Manifest::load(&member.join("Cargo.toml")));
+        if member_manifest.package_name() == Some(name) {
+            return Ok(member)
+        }
+    }
+    Err(format!("No package named {} in {}", name, path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use tar::Builder;
+
+    use kind::ChangeKind::*;
+    use report::Severity::*;
+    use tempdir::TempDir;
+    use {create_report, findings, write_crate};
+    use super::{Source, git};
+
+    const MANIFEST: &'static str = "[package]\nname = \"foo\"\nversion = \"0.1.0\"\n";
+
+    fn commit(repo: &Path, lib: &str) {
+        write_crate(repo, &[("Cargo.toml", MANIFEST), ("src/lib.rs", lib)]);
+        git(repo, &["add", "-A"]).unwrap();
+        git(repo, &["-c", "user.name=semverify", "-c", "user.email=semverify@example.com",
+            "commit", "-q", "-m", "update"]).unwrap();
+    }
+
+    #[test]
+    fn git_revisions() {
+        let dir = TempDir::new("semverify").unwrap();
+        git(dir.path(), &["init", "-q"]).unwrap();
+        commit(dir.path(), "pub fn foo() {} pub fn bar() {}");
+        commit(dir.path(), "pub fn foo() {}");
+
+        let old = Source::Git { repo: dir.path().to_owned(), rev: "HEAD~1".to_owned() }.checkout(None, None).unwrap();
+        let new = Source::Git { repo: dir.path().to_owned(), rev: "HEAD".to_owned() }.checkout(None, None).unwrap();
+        assert_eq!(findings(&create_report(&old.path, &new.path)), vec![(ItemRemoved, Major)]);
+    }
+
+    #[test]
+    fn workspace_members() {
+        let dir = TempDir::new("semverify").unwrap();
+        write_crate(dir.path(), &[
+            ("Cargo.toml", "[workspace]\nmembers = [\"crates/*\"]\n"),
+            ("crates/foo/Cargo.toml", MANIFEST),
+            ("crates/foo/src/lib.rs", ""),
+            ("crates/bar/Cargo.toml", "[package]\nname = \"bar\"\nversion = \"0.1.0\"\n"),
+            ("crates/bar/src/lib.rs", ""),
+        ]);
+
+        let source = Source::Path(dir.path().to_owned());
+        assert_eq!(source.checkout(Some("bar"), None).unwrap().path, dir.path().join("crates/bar"));
+        assert_eq!(source.checkout(Some("foo"), None).unwrap().path, dir.path().join("crates/foo"));
+        assert!(source.checkout(Some("baz"), None).is_err());
+    }
+
+    #[test]
+    fn published_crates() {
+        let dir = TempDir::new("semverify").unwrap();
+        let package = dir.path().join("package");
+        write_crate(&package, &[("Cargo.toml", MANIFEST), ("src/lib.rs", "pub fn foo() {}")]);
+
+        // a .crate file is a gzipped tarball of a single NAME-VERSION directory
+        let cache = dir.path().join("cargo/registry/cache/index");
+        fs::create_dir_all(&cache).unwrap();
+        let file = cache.join("foo-0.1.0.crate");
+        {
+            let mut tarball = Builder::new(GzEncoder::new(File::create(&file).unwrap(), Compression::Default));
+            for name in &["Cargo.toml", "src/lib.rs"] {
+                let mut contents = File::open(package.join(name)).unwrap();
+                tarball.append_file(format!("foo-0.1.0/{}", name), &mut contents).unwrap();
+            }
+            tarball.into_inner().unwrap().finish().unwrap();
+        }
+
+        let new = dir.path().join("new");
+        write_crate(&new, &[("Cargo.toml", MANIFEST), ("src/lib.rs", "pub fn foo() {} pub fn bar() {}")]);
+
+        let old = Source::parse(file.to_str().unwrap()).checkout(None, None).unwrap();
+        assert!(old.path.ends_with("foo-0.1.0"));
+        assert_eq!(findings(&create_report(&old.path, &new)), vec![(ItemAdded, Minor)]);
+
+        // and found by name in the registry cache
+        let cargo_home = dir.path().join("cargo");
+        let old = Source::parse("foo:0.1.0").checkout(None, Some(&cargo_home)).unwrap();
+        assert_eq!(findings(&create_report(&old.path, &new)), vec![(ItemAdded, Minor)]);
+        assert!(Source::parse("foo:0.2.0").checkout(None, Some(&cargo_home)).is_err());
+    }
+}
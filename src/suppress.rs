@@ -0,0 +1,260 @@
+//! suppression of intentional changes
+//!
+//! A `semverify.toml` file can list changes which are known and accepted,
+//! such as the removal of a `#[doc(hidden)]` item:
+//!
+//! ```toml
+//! [[suppress]]
+//! path = "internals::*"
+//! kind = "item-removed"
+//! reason = "hidden from the documentation, not part of the public API"
+//! ```
+//!
+//! `path` is the path of an item, or a module path followed by `::*` for
+//! everything in that module. A trait impl's path is its type's path and its
+//! trait's, as in `foo::Bar as std::clone::Clone`. `kind` is the ID of a kind
+//! of change. Either may be left out to match every path or every kind, but
+//! not both; leaving out the path is the way to accept findings about the
+//! crate as a whole, which have none. `reason` is required.
+//!
+//! Matched findings stay in the report, lowered to `Note` and marked as
+//! suppressed, so they no longer affect the highest severity.
+
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use toml;
+
+use kind::ChangeKind;
+use report::*;
+
+/// The conventional name of the suppression file.
+pub const FILE_NAME: &'static str = "semverify.toml";
+
+/// A single accepted change.
+#[derive(Clone, Debug)]
+pub struct Suppression {
+    pub path: Option<String>,
+    pub kind: Option<ChangeKind>,
+    pub reason: String,
+}
+
+/// The suppressions from a configuration file.
+#[derive(Clone, Debug)]
+pub struct Suppressions {
+    pub list: Vec<Suppression>,
+}
+
+impl Suppressions {
+    /// Read and parse the suppression file at the given path.
+    pub fn load(path: &Path) -> Result<Suppressions, String> {
+        let mut text = String::new();
+        if let Err(e) = File::open(path).and_then(|mut f| f.read_to_string(&mut text)) {
+            return Err(format!("Failed to read {}: {}", path.display(), e))
+        }
+        Suppressions::parse(path, &text)
+    }
+
+    /// Parse suppressions from the text of a file.
+    pub fn parse(path: &Path, text: &str) -> Result<Suppressions, String> {
+        let mut parser = toml::Parser::new(text);
+        let table = match parser.parse() {
+            Some(table) => table,
+            None => {
+                let errors: Vec<_> = parser.errors.iter().map(|e| e.to_string()).collect();
+                return Err(format!("Failed to parse {}: {}", path.display(), errors.join("; ")))
+            }
+        };
+        let entries = match table.get("suppress") {
+            None => return Ok(Suppressions { list: Vec::new() }),
+            Some(&toml::Value::Array(ref entries)) => entries,
+            Some(_) => return Err(format!("In {}: `suppress` must be an array of tables", path.display())),
+        };
+
+        let mut list = Vec::new();
+        for (idx, entry) in entries.iter().enumerate() {
+            let field = |name: &str| entry.lookup(name).and_then(toml::Value::as_str);
+            let kind = match field("kind") {
+                None => None,
+                Some(id) => match ChangeKind::from_id(id) {
+                    Some(kind) => Some(kind),
+                    None => return Err(format!("In {}: unknown kind of change {:?}", path.display(), id)),
+                },
+            };
+            let path_pattern = field("path");
+            if path_pattern.is_none() && kind.is_none() {
+                return Err(format!("In {}: suppression {} has neither a path nor a kind", path.display(), idx + 1))
+            }
+            let mut suppression = Suppression {
+                path: path_pattern.map(str::to_owned),
+                kind: kind,
+                reason: String::new(),
+            };
+            suppression.reason = match field("reason") {
+                Some(reason) if !reason.trim().is_empty() => reason.to_owned(),
+                _ => return Err(format!("In {}: suppression of {} has no reason", path.display(), suppression)),
+            };
+            list.push(suppression);
+        }
+        Ok(Suppressions { list: list })
+    }
+
+    /// Suppress every matching finding in the report.
+    ///
+    /// Suppressions which match nothing are noted, as they are likely out of
+    /// date.
+    pub fn apply(&self, r: &mut Report) {
+        self.apply_all(Some(r));
+    }
+
+    /// Suppress every matching finding in several reports, such as the
+    /// crate-wide report of a matrix and its report for each configuration.
+    ///
+    /// Suppressions which match nothing in any of them are noted once, in the
+    /// first report.
+    pub fn apply_all<'r, I>(&self, reports: I) where I: IntoIterator<Item=&'r mut Report> {
+        let mut reports = reports.into_iter();
+        let first = match reports.next() {
+            Some(first) => first,
+            None => return,
+        };
+        let mut used = vec![false; self.list.len()];
+        self.apply_to(first, None, &mut used);
+        for r in reports {
+            self.apply_to(r, None, &mut used);
+        }
+        for (suppression, _) in self.list.iter().zip(used).filter(|&(_, used)| !used) {
+            push!(first, Note, "suppression of {} matched nothing", suppression);
+        }
+    }
+
+    fn apply_to(&self, r: &mut Report, parent_path: Option<&str>, used: &mut [bool]) {
+        let path = r.item.path.clone().or(parent_path.map(str::to_owned));
+        if let Some(kind) = r.item.kind {
+            if r.item.severity > Severity::Note && r.item.suppressed.is_none() {
+                // every match is in use, though the first gives the reason
+                let mut reason = None;
+                for (idx, suppression) in self.list.iter().enumerate() {
+                    let path_matches = match (suppression.path.as_ref(), path.as_ref()) {
+                        (None, _) => true,
+                        (Some(pattern), Some(path)) => path_matches(pattern, path),
+                        (Some(_), None) => false,
+                    };
+                    if suppression.kind.map_or(true, |k| k == kind) && path_matches {
+                        used[idx] = true;
+                        reason = reason.or_else(|| Some(&suppression.reason));
+                    }
+                }
+                if let Some(reason) = reason {
+                    r.item.suppressed = Some(Suppressed {
+                        severity: r.item.severity,
+                        reason: reason.clone(),
+                    });
+                    r.item.severity = Severity::Note;
+                }
+            }
+        }
+        for child in &mut r.children {
+            self.apply_to(child, path.as_ref().map(|p| &**p), used);
+        }
+    }
+}
+
+impl fmt::Display for Suppression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.path.as_ref(), self.kind) {
+            (Some(path), Some(kind)) => write!(f, "{} [{}]", path, kind.id()),
+            (Some(path), None) => write!(f, "{}", path),
+            (None, Some(kind)) => write!(f, "[{}]", kind.id()),
+            (None, None) => write!(f, "everything"),
+        }
+    }
+}
+
+/// Whether an item path matches a suppression's path, which may end in
+/// `::*` to match everything in a module.
+fn path_matches(pattern: &str, path: &str) -> bool {
+    if pattern.ends_with("::*") {
+        let module = &pattern[..pattern.len() - 1];
+        path.starts_with(module)
+    } else {
+        pattern == path
+    }
+}
+
+#[test]
+fn paths() {
+    assert!(path_matches("foo::Bar", "foo::Bar"));
+    assert!(!path_matches("foo::Bar", "foo::Barrier"));
+    assert!(path_matches("foo::*", "foo::Bar"));
+    assert!(path_matches("foo::*", "foo::bar::Baz"));
+    assert!(!path_matches("foo::*", "foobar::Baz"));
+    assert!(!path_matches("foo::*", "foo"));
+}
+
+#[test]
+fn overlapping_suppressions() {
+    let suppressions = Suppressions::parse(Path::new(FILE_NAME), r#"
+        [[suppress]]
+        path = "foo::*"
+        reason = "unstable"
+
+        [[suppress]]
+        path = "foo::Bar"
+        kind = "item-removed"
+        reason = "renamed"
+    "#).unwrap();
+    let mut r = Report::new();
+    let _ = push!(r, Major(ItemRemoved)).with_path("foo::Bar".to_owned());
+    suppressions.apply(&mut r);
+
+    // the first match gives the reason, but neither is reported as unused
+    assert_eq!(r.children.len(), 1);
+    assert_eq!(r.children[0].item.suppressed.as_ref().map(|s| &*s.reason), Some("unstable"));
+}
+
+#[test]
+fn crate_level_findings() {
+    let suppressions = Suppressions::parse(Path::new(FILE_NAME), r#"
+        [[suppress]]
+        kind = "crate-cfg-narrowed"
+        reason = "dropped support for windows"
+    "#).unwrap();
+    let mut r = Report::new();
+    push!(r, Major(CrateCfgNarrowed), "New crate reduces #[cfg] coverage");
+    let _ = push!(r, Major(CfgNarrowed)).with_path("foo".to_owned());
+    suppressions.apply(&mut r);
+
+    // a finding without a path can only be matched by kind
+    assert_eq!(r.children[0].item.severity, Severity::Note);
+    assert!(r.children[0].item.suppressed.is_some());
+    assert_eq!(r.children[1].item.severity, Severity::Major);
+
+    assert!(Suppressions::parse(Path::new(FILE_NAME), "[[suppress]]\nreason = \"everything\"").is_err());
+}
+
+#[test]
+fn several_reports() {
+    let suppressions = Suppressions::parse(Path::new(FILE_NAME), r#"
+        [[suppress]]
+        path = "foo"
+        reason = "only on windows"
+
+        [[suppress]]
+        path = "bar"
+        reason = "removed long ago"
+    "#).unwrap();
+    let (mut crate_wide, mut unix, mut windows) = (Report::new(), Report::new(), Report::new());
+    push!(crate_wide, Warning, "Could not parse macro arms");
+    let _ = push!(windows, Major(ItemRemoved)).with_path("foo".to_owned());
+    suppressions.apply_all(vec![&mut crate_wide, &mut unix, &mut windows]);
+
+    // a suppression matching in any report is used, and one matching in none
+    // is noted once
+    assert_eq!(windows.children[0].item.severity, Severity::Note);
+    assert_eq!(crate_wide.children.len(), 2);
+    assert_eq!(crate_wide.children[1].item.text, "suppression of bar matched nothing");
+    assert!(unix.children.is_empty());
+}
@@ -0,0 +1,118 @@
+//! known compilation targets
+//!
+//! The `target_*` cfg keys are not independent: `target_os = "windows"`
+//! implies `target_family = "windows"`, and no target is both `unix` and
+//! `windows`. This table of real targets lets cfg reasoning rule out
+//! combinations which no target has.
+//!
+//! Targets missing here are still accounted for: a cfg mentioning a value
+//! which no known target has is assumed to be satisfiable by some unknown
+//! target.
+
+/// The cfg values of a compilation target.
+#[derive(Debug)]
+pub struct Target {
+    pub triple: &'static str,
+    pub arch: &'static str,
+    pub os: &'static str,
+    pub family: &'static str,
+    pub env: &'static str,
+    pub endian: &'static str,
+    pub pointer_width: &'static str,
+    pub vendor: &'static str,
+    /// The sizes for which atomic operations are available
+    pub has_atomic: &'static [&'static str],
+}
+
+const ATOMIC_64: &'static [&'static str] = &["8", "16", "32", "64", "ptr"];
+const ATOMIC_32: &'static [&'static str] = &["8", "16", "32", "ptr"];
+
+macro_rules! targets {
+    ($($triple:expr => $arch:expr, $os:expr, $family:expr, $env:expr, $endian:expr, $width:expr, $vendor:expr, $atomic:expr;)*) => {
+        /// Every known target.
+        pub const TARGETS: &'static [Target] = &[$(
+            Target {
+                triple: $triple,
+                arch: $arch,
+                os: $os,
+                family: $family,
+                env: $env,
+                endian: $endian,
+                pointer_width: $width,
+                vendor: $vendor,
+                has_atomic: $atomic,
+            },
+        )*];
+    }
+}
+
+targets! {
+    // triple                       => arch         os            family     env     endian    width vendor     atomics
+    "x86_64-unknown-linux-gnu"      => "x86_64",    "linux",      "unix",    "gnu",  "little", "64", "unknown", ATOMIC_64;
+    "x86_64-unknown-linux-musl"     => "x86_64",    "linux",      "unix",    "musl", "little", "64", "unknown", ATOMIC_64;
+    "i686-unknown-linux-gnu"        => "x86",       "linux",      "unix",    "gnu",  "little", "32", "unknown", ATOMIC_64;
+    "i686-unknown-linux-musl"       => "x86",       "linux",      "unix",    "musl", "little", "32", "unknown", ATOMIC_64;
+    "i586-unknown-linux-gnu"        => "x86",       "linux",      "unix",    "gnu",  "little", "32", "unknown", ATOMIC_64;
+    "aarch64-unknown-linux-gnu"     => "aarch64",   "linux",      "unix",    "gnu",  "little", "64", "unknown", ATOMIC_64;
+    "arm-unknown-linux-gnueabi"     => "arm",       "linux",      "unix",    "gnu",  "little", "32", "unknown", ATOMIC_64;
+    "arm-unknown-linux-gnueabihf"   => "arm",       "linux",      "unix",    "gnu",  "little", "32", "unknown", ATOMIC_64;
+    "armv7-unknown-linux-gnueabihf" => "arm",       "linux",      "unix",    "gnu",  "little", "32", "unknown", ATOMIC_64;
+    "mips-unknown-linux-gnu"        => "mips",      "linux",      "unix",    "gnu",  "big",    "32", "unknown", ATOMIC_32;
+    "mipsel-unknown-linux-gnu"      => "mips",      "linux",      "unix",    "gnu",  "little", "32", "unknown", ATOMIC_32;
+    "powerpc-unknown-linux-gnu"     => "powerpc",   "linux",      "unix",    "gnu",  "big",    "32", "unknown", ATOMIC_32;
+    "powerpc64-unknown-linux-gnu"   => "powerpc64", "linux",      "unix",    "gnu",  "big",    "64", "unknown", ATOMIC_64;
+    "powerpc64le-unknown-linux-gnu" => "powerpc64", "linux",      "unix",    "gnu",  "little", "64", "unknown", ATOMIC_64;
+    "s390x-unknown-linux-gnu"       => "s390x",     "linux",      "unix",    "gnu",  "big",    "64", "unknown", ATOMIC_64;
+    "arm-linux-androideabi"         => "arm",       "android",    "unix",    "",     "little", "32", "unknown", ATOMIC_64;
+    "aarch64-linux-android"         => "aarch64",   "android",    "unix",    "",     "little", "64", "unknown", ATOMIC_64;
+    "i686-linux-android"            => "x86",       "android",    "unix",    "",     "little", "32", "unknown", ATOMIC_64;
+    "x86_64-apple-darwin"           => "x86_64",    "macos",      "unix",    "",     "little", "64", "apple",   ATOMIC_64;
+    "i686-apple-darwin"             => "x86",       "macos",      "unix",    "",     "little", "32", "apple",   ATOMIC_64;
+    "x86_64-apple-ios"              => "x86_64",    "ios",        "unix",    "",     "little", "64", "apple",   ATOMIC_64;
+    "aarch64-apple-ios"             => "aarch64",   "ios",        "unix",    "",     "little", "64", "apple",   ATOMIC_64;
+    "armv7-apple-ios"               => "arm",       "ios",        "unix",    "",     "little", "32", "apple",   ATOMIC_64;
+    "x86_64-pc-windows-msvc"        => "x86_64",    "windows",    "windows", "msvc", "little", "64", "pc",      ATOMIC_64;
+    "i686-pc-windows-msvc"          => "x86",       "windows",    "windows", "msvc", "little", "32", "pc",      ATOMIC_64;
+    "x86_64-pc-windows-gnu"         => "x86_64",    "windows",    "windows", "gnu",  "little", "64", "pc",      ATOMIC_64;
+    "i686-pc-windows-gnu"           => "x86",       "windows",    "windows", "gnu",  "little", "32", "pc",      ATOMIC_64;
+    "x86_64-unknown-freebsd"        => "x86_64",    "freebsd",    "unix",    "",     "little", "64", "unknown", ATOMIC_64;
+    "i686-unknown-freebsd"          => "x86",       "freebsd",    "unix",    "",     "little", "32", "unknown", ATOMIC_64;
+    "x86_64-unknown-netbsd"         => "x86_64",    "netbsd",     "unix",    "",     "little", "64", "unknown", ATOMIC_64;
+    "x86_64-unknown-openbsd"        => "x86_64",    "openbsd",    "unix",    "",     "little", "64", "unknown", ATOMIC_64;
+    "x86_64-unknown-dragonfly"      => "x86_64",    "dragonfly",  "unix",    "",     "little", "64", "unknown", ATOMIC_64;
+    "x86_64-unknown-bitrig"         => "x86_64",    "bitrig",     "unix",    "",     "little", "64", "unknown", ATOMIC_64;
+    "x86_64-sun-solaris"            => "x86_64",    "solaris",    "unix",    "",     "little", "64", "sun",     ATOMIC_64;
+    "asmjs-unknown-emscripten"      => "asmjs",     "emscripten", "unix",    "",     "little", "32", "unknown", ATOMIC_32;
+}
+
+impl Target {
+    /// Look up a target by its triple.
+    pub fn find(triple: &str) -> Option<&'static Target> {
+        TARGETS.iter().find(|target| target.triple == triple)
+    }
+
+    /// Whether `#[cfg(key = "value")]` applies to this target.
+    pub fn has(&self, key: &str, value: &str) -> bool {
+        match key {
+            "target_arch" => self.arch == value,
+            "target_os" => self.os == value,
+            "target_family" => self.family == value,
+            "target_env" => self.env == value,
+            "target_endian" => self.endian == value,
+            "target_pointer_width" => self.pointer_width == value,
+            "target_vendor" => self.vendor == value,
+            "target_has_atomic" => self.has_atomic.contains(&value),
+            _ => false,
+        }
+    }
+}
+
+/// Whether any known target has the given value for a cfg key.
+pub fn is_known(key: &str, value: &str) -> bool {
+    TARGETS.iter().any(|target| target.has(key, value))
+}
+
+/// Whether a target can have several values for a cfg key at once.
+pub fn is_multi_valued(key: &str) -> bool {
+    key == "target_has_atomic"
+}
@@ -0,0 +1,908 @@
+//! structural comparison of types
+//!
+//! Types are compared node by node rather than by their printed form. Paths
+//! are resolved through the imports of the module using them, re-exports and
+//! the prelude, and the local type aliases visible there are expanded, so
+//! that e.g. `Vec<u8>` and `::std::vec::Vec<u8>` are recognized as the same
+//! type. Type and lifetime parameters are matched by their position in the
+//! generics.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use syntax::ast::*;
+use syntax::codemap;
+use syntax::fold::{self, Folder};
+use syntax::parse::token;
+use syntax::print::pprust;
+use syntax::ptr::P;
+
+use compare::{resolved_bounds, is_type_param, type_param_index};
+
+/// Aliases and re-exports are not followed further than this, as a guard
+/// against cycles.
+const MAX_ALIAS_DEPTH: usize = 16;
+
+/// Types and traits in the prelude, which may be named without an import.
+const PRELUDE: &'static [(&'static str, &'static [&'static str])] = &[
+    ("Box", &["std", "boxed", "Box"]),
+    ("Option", &["std", "option", "Option"]),
+    ("Result", &["std", "result", "Result"]),
+    ("String", &["std", "string", "String"]),
+    ("Vec", &["std", "vec", "Vec"]),
+    ("Copy", &["std", "marker", "Copy"]),
+    ("Send", &["std", "marker", "Send"]),
+    ("Sized", &["std", "marker", "Sized"]),
+    ("Sync", &["std", "marker", "Sync"]),
+    ("Drop", &["std", "ops", "Drop"]),
+    ("Fn", &["std", "ops", "Fn"]),
+    ("FnMut", &["std", "ops", "FnMut"]),
+    ("FnOnce", &["std", "ops", "FnOnce"]),
+    ("Clone", &["std", "clone", "Clone"]),
+    ("PartialEq", &["std", "cmp", "PartialEq"]),
+    ("PartialOrd", &["std", "cmp", "PartialOrd"]),
+    ("Eq", &["std", "cmp", "Eq"]),
+    ("Ord", &["std", "cmp", "Ord"]),
+    ("AsRef", &["std", "convert", "AsRef"]),
+    ("AsMut", &["std", "convert", "AsMut"]),
+    ("Into", &["std", "convert", "Into"]),
+    ("From", &["std", "convert", "From"]),
+    ("Default", &["std", "default", "Default"]),
+    ("Iterator", &["std", "iter", "Iterator"]),
+    ("Extend", &["std", "iter", "Extend"]),
+    ("IntoIterator", &["std", "iter", "IntoIterator"]),
+    ("DoubleEndedIterator", &["std", "iter", "DoubleEndedIterator"]),
+    ("ExactSizeIterator", &["std", "iter", "ExactSizeIterator"]),
+    ("ToOwned", &["std", "borrow", "ToOwned"]),
+    ("ToString", &["std", "string", "ToString"]),
+];
+
+/// The relationship of a new type to the old type it replaced.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Relation {
+    /// The types are the same.
+    Equal,
+    /// The new type accepts everything the old type did, and more.
+    MoreGeneral,
+    /// The old type accepted things which the new type does not.
+    LessGeneral,
+    /// The types are unrelated.
+    Incompatible,
+}
+
+impl Relation {
+    /// Combine the relations of two parts of a type.
+    pub fn and(self, other: Relation) -> Relation {
+        use self::Relation::*;
+        match (self, other) {
+            (Equal, x) | (x, Equal) => x,
+            (x, y) if x == y => x,
+            _ => Incompatible,
+        }
+    }
+}
+
+/// Per-crate information used to normalize types.
+///
+/// Paths are kept as lists of names from the crate root, which is also where
+/// `use` paths and global paths start.
+struct Env<'a> {
+    /// The names in scope in each module, from its items and imports, with
+    /// the paths they refer to, or `None` if a name is ambiguous
+    scopes: HashMap<Vec<String>, HashMap<String, Option<Vec<String>>>>,
+    /// Type aliases by the path they are defined at, or `None` if defined
+    /// more than once
+    aliases: HashMap<Vec<String>, Option<(&'a Generics, &'a Ty)>>,
+}
+
+impl<'a> Env<'a> {
+    fn new(krate: &'a Crate) -> Env<'a> {
+        let mut env = Env { scopes: HashMap::new(), aliases: HashMap::new() };
+        env.walk(&krate.module, Vec::new());
+        env
+    }
+
+    fn walk(&mut self, module: &'a Mod, path: Vec<String>) {
+        use syntax::ast::ItemKind::*;
+        for item in &module.items {
+            let name = item.ident.name.to_string();
+            let mut item_path = path.clone();
+            item_path.push(name.clone());
+            match item.node {
+                Use(ref view_path) => self.add_import(&path, view_path),
+                ExternCrate(original) => {
+                    let krate = original.unwrap_or(item.ident.name).to_string();
+                    self.add_name(&path, name, vec![krate]);
+                }
+                Mod(ref inner) => {
+                    self.add_name(&path, name, item_path.clone());
+                    self.walk(inner, item_path);
+                }
+                Ty(ref ty, ref generics) => {
+                    let value = if self.aliases.contains_key(&item_path) {
+                        None
+                    } else {
+                        Some((generics, &**ty))
+                    };
+                    self.aliases.insert(item_path.clone(), value);
+                    self.add_name(&path, name, item_path);
+                }
+                Struct(..) | Enum(..) | Trait(..) => self.add_name(&path, name, item_path),
+                _ => {}
+            }
+        }
+    }
+
+    /// Bring a name into scope in a module.
+    fn add_name(&mut self, module: &[String], name: String, target: Vec<String>) {
+        let value = Some(target);
+        let entry = self.scopes.entry(module.to_vec()).or_insert_with(HashMap::new)
+            .entry(name).or_insert(value.clone());
+        if *entry != value {
+            *entry = None;
+        }
+    }
+
+    fn add_import(&mut self, module: &[String], view_path: &ViewPath) {
+        match view_path.node {
+            ViewPath_::ViewPathSimple(ident, ref path) => {
+                self.add_name(module, ident.name.to_string(), absolute(module, &path_names(path)));
+            }
+            ViewPath_::ViewPathGlob(_) => {}
+            ViewPath_::ViewPathList(ref path, ref items) => for item in items {
+                let mut full = absolute(module, &path_names(path));
+                match item.node {
+                    PathListItemKind::Ident { name, rename, .. } => {
+                        full.push(name.name.to_string());
+                        self.add_name(module, rename.unwrap_or(name).name.to_string(), full);
+                    }
+                    PathListItemKind::Mod { rename, .. } => {
+                        let name = match rename {
+                            Some(ident) => ident.name.to_string(),
+                            None => match full.last() { Some(last) => last.clone(), None => continue },
+                        };
+                        self.add_name(module, name, full);
+                    }
+                }
+            },
+        }
+    }
+
+    /// The path a name in scope in a module refers to, if it is unambiguous.
+    fn lookup(&self, module: &[String], name: &str) -> Option<&Vec<String>> {
+        match self.scopes.get(module).and_then(|scope| scope.get(name)) {
+            Some(&Some(ref target)) => Some(target),
+            _ => None,
+        }
+    }
+
+    /// Follow re-exports in a path from the crate root to where the item it
+    /// names is defined. Paths leaving the crate are returned as they are.
+    fn canonical(&self, path: &[String], depth: usize) -> Vec<String> {
+        let mut current: Vec<String> = Vec::new();
+        for name in path {
+            let target = if depth < MAX_ALIAS_DEPTH { self.lookup(&current, name).cloned() } else { None };
+            current.push(name.clone());
+            if let Some(target) = target {
+                if target != current {
+                    current = self.canonical(&target, depth + 1);
+                }
+            }
+        }
+        current
+    }
+
+    /// Resolve a path used in `module` to where the item it names is
+    /// defined. Returns `None` if the path does not start with a name known
+    /// to be in scope, such as one brought in by a glob import.
+    fn resolve(&self, module: &[String], path: &Path) -> Option<Vec<String>> {
+        let names = path_names(path);
+        let from_root = match names.first() {
+            None => return None,
+            Some(_) if path.global => names.clone(),
+            Some(first) if first == "self" || first == "super" => absolute(module, &names),
+            Some(first) => match self.lookup(module, first) {
+                Some(target) => {
+                    let mut full = target.clone();
+                    full.extend(names[1..].iter().cloned());
+                    full
+                }
+                None if names.len() == 1 => {
+                    return PRELUDE.iter()
+                        .find(|&&(short, _)| short == &**first)
+                        .map(|&(_, full)| full.iter().map(|s| s.to_string()).collect())
+                }
+                None => return None,
+            },
+        };
+        let mut resolved = self.canonical(&from_root, 0);
+        // Facade crates re-exported by std
+        if resolved.first().map_or(false, |n| n == "core" || n == "collections" || n == "alloc") {
+            resolved[0] = "std".to_owned();
+        }
+        Some(resolved)
+    }
+
+    /// Resolve a path through imports and the prelude to a list of names.
+    ///
+    /// Paths which cannot be resolved are left as they are written, so they
+    /// are only equal to paths written the same way.
+    fn normalize(&self, module: &[String], path: &Path) -> Vec<String> {
+        self.resolve(module, path).unwrap_or_else(|| path_names(path))
+    }
+
+    /// Expand `ty` if it names a local type alias visible in `module`.
+    fn expand_alias(&self, module: &[String], generics: &Generics, ty: &Ty) -> Option<P<Ty>> {
+        let path = match ty.node {
+            TyKind::Path(None, ref path) => path,
+            _ => return None,
+        };
+        if param_name(path).map_or(false, |name| is_type_param(generics, name)) {
+            return None
+        }
+        let full = match self.resolve(module, path) {
+            Some(full) => full,
+            None => return None,
+        };
+        let (alias_generics, target) = match self.aliases.get(&full) {
+            Some(&Some(alias)) => alias,
+            _ => return None,
+        };
+
+        // Pair each of the alias's parameters with its argument or default
+        let args: &[P<Ty>] = match path.segments.last().map(|segment| &segment.parameters) {
+            Some(&PathParameters::AngleBracketed(ref data)) => &data.types,
+            _ => return None,
+        };
+        if args.len() > alias_generics.ty_params.len() {
+            return None
+        }
+        let mut params = Vec::new();
+        for (idx, param) in alias_generics.ty_params.iter().enumerate() {
+            match args.get(idx).or(param.default.as_ref()) {
+                Some(arg) => params.push((param.ident.name, arg.clone())),
+                None => return None,
+            }
+        }
+        let alias_module = &full[..full.len() - 1];
+        Some(Substitute { params: &params, env: self, module: alias_module }.fold_ty(P(target.clone())))
+    }
+}
+
+/// Replaces type parameters with their arguments, and makes the other paths
+/// in an alias global so that they keep their meaning outside its module.
+struct Substitute<'b> {
+    params: &'b [(Name, P<Ty>)],
+    env: &'b Env<'b>,
+    /// The module the alias is defined in
+    module: &'b [String],
+}
+
+impl<'b> Substitute<'b> {
+    fn globalize(&self, path: &mut Path) {
+        if path.segments.first().map_or(true, |s| self.params.iter().any(|&(n, _)| n == s.identifier.name)) {
+            return
+        }
+        if let Some(names) = self.env.resolve(self.module, path) {
+            let parameters = path.segments.last().unwrap().parameters.clone();
+            path.global = true;
+            path.segments = names.iter().map(|name| PathSegment {
+                identifier: token::str_to_ident(name),
+                parameters: PathParameters::none(),
+            }).collect();
+            path.segments.last_mut().unwrap().parameters = parameters;
+        }
+    }
+}
+
+impl<'b> Folder for Substitute<'b> {
+    fn fold_ty(&mut self, ty: P<Ty>) -> P<Ty> {
+        if let TyKind::Path(None, ref path) = ty.node {
+            if let Some(name) = param_name(path) {
+                if let Some(&(_, ref arg)) = self.params.iter().find(|&&(n, _)| n == name) {
+                    return arg.clone()
+                }
+            }
+        }
+        fold::noop_fold_ty(ty, self).map(|mut ty| {
+            if let TyKind::Path(None, ref mut path) = ty.node {
+                self.globalize(path);
+            }
+            ty
+        })
+    }
+
+    fn fold_mac(&mut self, mac: Mac) -> Mac {
+        fold::noop_fold_mac(mac, self)
+    }
+}
+
+/// The generics and modules in scope on either side of a comparison.
+struct Scope<'g> {
+    old: &'g Generics,
+    new: &'g Generics,
+    old_module: &'g [String],
+    new_module: &'g [String],
+    /// Whether elided lifetimes are `'static`, as in consts and statics
+    elided_static: bool,
+}
+
+/// Compares types from an old crate to types from a new crate.
+pub struct TypeComparer<'a> {
+    old: Env<'a>,
+    new: Env<'a>,
+    /// The modules defining the old and new items being compared, which
+    /// paths in their types are relative to
+    modules: RefCell<(Vec<String>, Vec<String>)>,
+}
+
+impl<'a> TypeComparer<'a> {
+    pub fn new(old: &'a Crate, new: &'a Crate) -> TypeComparer<'a> {
+        TypeComparer {
+            old: Env::new(old),
+            new: Env::new(new),
+            modules: RefCell::new((Vec::new(), Vec::new())),
+        }
+    }
+
+    /// Set the modules defining the old and new items whose types are
+    /// compared next.
+    pub fn set_modules(&self, old: &[Name], new: &[Name]) {
+        *self.modules.borrow_mut() = (module_names(old), module_names(new));
+    }
+
+    /// Resolve a path used in a module of the old crate to the path of the
+    /// item it names, from the crate root. Paths which cannot be resolved
+    /// are left as they are written.
+    pub fn resolve_old(&self, module: &[Name], path: &Path) -> Vec<String> {
+        self.old.normalize(&module_names(module), path)
+    }
+
+    /// Resolve a path used in a module of the new crate, like `resolve_old`.
+    pub fn resolve_new(&self, module: &[Name], path: &Path) -> Vec<String> {
+        self.new.normalize(&module_names(module), path)
+    }
+
+    /// Resolve a path used in the old item being compared, in the module
+    /// given to `set_modules`.
+    pub fn resolve_old_here(&self, path: &Path) -> Vec<String> {
+        self.old.normalize(&self.modules.borrow().0, path)
+    }
+
+    /// Resolve a path used in the new item being compared, like
+    /// `resolve_old_here`.
+    pub fn resolve_new_here(&self, path: &Path) -> Vec<String> {
+        self.new.normalize(&self.modules.borrow().1, path)
+    }
+
+    /// Compare two types with no type parameters in scope.
+    pub fn compare(&self, old: &Ty, new: &Ty) -> Relation {
+        let empty = Generics::default();
+        self.compare_with(&empty, &empty, false, old, new)
+    }
+
+    /// Compare the types of two consts or statics.
+    pub fn compare_static(&self, old: &Ty, new: &Ty) -> Relation {
+        let empty = Generics::default();
+        self.compare_with(&empty, &empty, true, old, new)
+    }
+
+    /// Compare two types in the scope of their respective generics.
+    ///
+    /// A type parameter introduced in the new generics which replaces a
+    /// concrete type is `MoreGeneral`; checking its bounds is left to the
+    /// caller.
+    pub fn compare_generic(&self, (old, old_generics): (&Ty, &Generics), (new, new_generics): (&Ty, &Generics)) -> Relation {
+        self.compare_with(old_generics, new_generics, false, old, new)
+    }
+
+    fn compare_with(&self, old_generics: &Generics, new_generics: &Generics, elided_static: bool, old: &Ty, new: &Ty) -> Relation {
+        let modules = self.modules.borrow();
+        let scope = Scope {
+            old: old_generics,
+            new: new_generics,
+            old_module: &modules.0,
+            new_module: &modules.1,
+            elided_static: elided_static,
+        };
+        self.compare_in(&scope, old, new, 0)
+    }
+
+    fn compare_in(&self, scope: &Scope, old: &Ty, new: &Ty, depth: usize) -> Relation {
+        use self::Relation::*;
+
+        let (old, new) = (strip_parens(old), strip_parens(new));
+        if depth < MAX_ALIAS_DEPTH {
+            let old_expanded = self.old.expand_alias(scope.old_module, scope.old, old);
+            let new_expanded = self.new.expand_alias(scope.new_module, scope.new, new);
+            if old_expanded.is_some() || new_expanded.is_some() {
+                return self.compare_in(scope,
+                    old_expanded.as_ref().map_or(old, |t| &**t),
+                    new_expanded.as_ref().map_or(new, |t| &**t),
+                    depth + 1)
+            }
+        }
+
+        match (&old.node, &new.node) {
+            (&TyKind::Path(None, ref old_path), &TyKind::Path(None, ref new_path)) => {
+                self.compare_paths(scope, old, old_path, new_path, depth)
+            }
+            (_, &TyKind::Path(None, ref new_path)) => self.compare_new_param(scope, old, new_path, depth),
+            (&TyKind::Path(None, ref old_path), _) => {
+                if param_name(old_path).map_or(false, |n| is_type_param(scope.old, n)) {
+                    LessGeneral
+                } else {
+                    Incompatible
+                }
+            }
+            (&TyKind::Vec(ref old_ty), &TyKind::Vec(ref new_ty)) => self.compare_in(scope, old_ty, new_ty, depth),
+            (&TyKind::FixedLengthVec(ref old_ty, ref old_len), &TyKind::FixedLengthVec(ref new_ty, ref new_len)) => {
+                if pprust::expr_to_string(old_len) != pprust::expr_to_string(new_len) {
+                    return Incompatible
+                }
+                self.compare_in(scope, old_ty, new_ty, depth)
+            }
+            (&TyKind::Ptr(ref old_mt), &TyKind::Ptr(ref new_mt)) => {
+                if old_mt.mutbl != new_mt.mutbl {
+                    return Incompatible
+                }
+                self.compare_in(scope, &old_mt.ty, &new_mt.ty, depth)
+            }
+            (&TyKind::Rptr(ref old_lt, ref old_mt), &TyKind::Rptr(ref new_lt, ref new_mt)) => {
+                if old_mt.mutbl != new_mt.mutbl {
+                    return Incompatible
+                }
+                compare_lifetimes(scope, old_lt.as_ref(), new_lt.as_ref())
+                    .and(self.compare_in(scope, &old_mt.ty, &new_mt.ty, depth))
+            }
+            (&TyKind::Tup(ref old_tys), &TyKind::Tup(ref new_tys)) => {
+                if old_tys.len() != new_tys.len() {
+                    return Incompatible
+                }
+                old_tys.iter().zip(new_tys).fold(Equal, |rel, (o, n)| rel.and(self.compare_in(scope, o, n, depth)))
+            }
+            (&TyKind::BareFn(ref old_fn), &TyKind::BareFn(ref new_fn)) => {
+                if old_fn.unsafety != new_fn.unsafety || old_fn.abi != new_fn.abi {
+                    return Incompatible
+                }
+                // Function pointers are compared invariantly
+                if self.compare_decls(scope, &old_fn.decl, &new_fn.decl, depth) == Equal { Equal } else { Incompatible }
+            }
+            (&TyKind::ObjectSum(ref old_ty, ref old_bounds), &TyKind::ObjectSum(ref new_ty, ref new_bounds)) => {
+                if !self.same_bounds(scope, old_bounds, new_bounds) {
+                    return Incompatible
+                }
+                self.compare_in(scope, old_ty, new_ty, depth)
+            }
+            (&TyKind::PolyTraitRef(ref old_bounds), &TyKind::PolyTraitRef(ref new_bounds)) => {
+                if self.same_bounds(scope, old_bounds, new_bounds) { Equal } else { Incompatible }
+            }
+            // Qualified paths, macros, and anything else are compared as printed
+            _ => if pprust::ty_to_string(old) == pprust::ty_to_string(new) { Equal } else { Incompatible },
+        }
+    }
+
+    fn same_bounds(&self, scope: &Scope, old: &[TyParamBound], new: &[TyParamBound]) -> bool {
+        resolved_bounds(old, &|path| self.old.normalize(scope.old_module, path)) ==
+            resolved_bounds(new, &|path| self.new.normalize(scope.new_module, path))
+    }
+
+    fn compare_paths(&self, scope: &Scope, old_ty: &Ty, old: &Path, new: &Path, depth: usize) -> Relation {
+        use self::Relation::*;
+
+        // Type parameters
+        let old_param = param_name(old).and_then(|n| if is_type_param(scope.old, n) { Some(n) } else { None });
+        let new_param = param_name(new).and_then(|n| if is_type_param(scope.new, n) { Some(n) } else { None });
+        match (old_param, new_param) {
+            // Parameters are matched by position, so renaming one is harmless
+            (Some(old_name), Some(new_name)) => {
+                let same = type_param_index(scope.old, old_name) == type_param_index(scope.new, new_name);
+                return if same { Equal } else { Incompatible }
+            }
+            (Some(_), None) => return LessGeneral,
+            (None, Some(_)) => return self.compare_new_param(scope, old_ty, new, depth),
+            (None, None) => {}
+        }
+
+        // The paths name the same type if they resolve to the same place, or
+        // if one crate's path to the type leads to the other's, as when a type
+        // is moved and re-exported at its old path
+        let old_names = self.old.normalize(scope.old_module, old);
+        let new_names = self.new.normalize(scope.new_module, new);
+        let same = old_names == new_names ||
+            self.new.canonical(&old_names, 0) == new_names ||
+            self.old.canonical(&new_names, 0) == old_names;
+        if !same {
+            return Incompatible
+        }
+        match (old.segments.last(), new.segments.last()) {
+            (Some(old_seg), Some(new_seg)) => self.compare_parameters(scope, &old_seg.parameters, &new_seg.parameters, depth),
+            _ => Equal,
+        }
+    }
+
+    /// Compare a concrete type to a path which may be a new type parameter.
+    fn compare_new_param(&self, scope: &Scope, old: &Ty, new: &Path, depth: usize) -> Relation {
+        use self::Relation::*;
+
+        let name = match param_name(new) {
+            Some(name) if type_param_index(scope.new, name).map_or(false, |idx| idx >= scope.old.ty_params.len()) => name,
+            _ => return Incompatible,
+        };
+        let default = scope.new.ty_params.iter()
+            .find(|p| p.ident.name == name)
+            .and_then(|p| p.default.as_ref());
+        match default {
+            // Foo(pub u8) to Foo<T = u8>(pub T) is a generalization, but
+            // Foo(pub u8) to Foo<T = i8>(pub T) is not.
+            Some(default) => {
+                let old_scope = Scope {
+                    old: scope.old,
+                    new: scope.old,
+                    old_module: scope.old_module,
+                    new_module: scope.new_module,
+                    elided_static: scope.elided_static,
+                };
+                if self.compare_in(&old_scope, old, default, depth) == Equal { MoreGeneral } else { Incompatible }
+            }
+            None => MoreGeneral,
+        }
+    }
+
+    fn compare_parameters(&self, scope: &Scope, old: &PathParameters, new: &PathParameters, depth: usize) -> Relation {
+        use self::Relation::*;
+        match (old, new) {
+            (&PathParameters::AngleBracketed(ref old), &PathParameters::AngleBracketed(ref new)) => {
+                if old.types.len() != new.types.len() || old.bindings.len() != new.bindings.len() {
+                    return Incompatible
+                }
+                let mut rel = Equal;
+                // lifetimes left out of a path are elided, which is not
+                // tracked through paths
+                if !old.lifetimes.is_empty() && !new.lifetimes.is_empty() {
+                    if old.lifetimes.len() != new.lifetimes.len() {
+                        return Incompatible
+                    }
+                    for (o, n) in old.lifetimes.iter().zip(&new.lifetimes) {
+                        rel = rel.and(compare_lifetimes(scope, Some(o), Some(n)));
+                    }
+                }
+                for (o, n) in old.types.iter().zip(new.types.iter()) {
+                    rel = rel.and(self.compare_in(scope, o, n, depth));
+                }
+                for (o, n) in old.bindings.iter().zip(new.bindings.iter()) {
+                    if o.ident.name != n.ident.name {
+                        return Incompatible
+                    }
+                    rel = rel.and(self.compare_in(scope, &o.ty, &n.ty, depth));
+                }
+                rel
+            }
+            (&PathParameters::Parenthesized(ref old), &PathParameters::Parenthesized(ref new)) => {
+                if old.inputs.len() != new.inputs.len() {
+                    return Incompatible
+                }
+                let mut rel = Equal;
+                for (o, n) in old.inputs.iter().zip(&new.inputs) {
+                    rel = rel.and(self.compare_in(scope, o, n, depth));
+                }
+                match (&old.output, &new.output) {
+                    (&Some(ref o), &Some(ref n)) => rel = rel.and(self.compare_in(scope, o, n, depth)),
+                    (&None, &None) => {}
+                    _ => return Incompatible,
+                }
+                if rel == Equal { Equal } else { Incompatible }
+            }
+            _ => Incompatible,
+        }
+    }
+
+    fn compare_decls(&self, scope: &Scope, old: &FnDecl, new: &FnDecl, depth: usize) -> Relation {
+        use self::Relation::*;
+        if old.inputs.len() != new.inputs.len() || old.variadic != new.variadic {
+            return Incompatible
+        }
+        let mut rel = Equal;
+        for (o, n) in old.inputs.iter().zip(&new.inputs) {
+            rel = rel.and(self.compare_in(scope, &o.ty, &n.ty, depth));
+        }
+        match (&old.output, &new.output) {
+            (&FunctionRetTy::Ty(ref o), &FunctionRetTy::Ty(ref n)) => rel.and(self.compare_in(scope, o, n, depth)),
+            (&FunctionRetTy::Ty(_), _) | (_, &FunctionRetTy::Ty(_)) => Incompatible,
+            _ => rel,
+        }
+    }
+}
+
+/// Named lifetimes are matched by their position in the generics, like type
+/// parameters, and `'static` only matches itself.
+///
+/// Whether trading one lifetime for another is more or less general depends
+/// on the variance of where it appears, which is not tracked, so any such
+/// change is incompatible.
+fn compare_lifetimes(scope: &Scope, old: Option<&Lifetime>, new: Option<&Lifetime>) -> Relation {
+    let old = lifetime_ref(scope.old, scope.elided_static, old);
+    let new = lifetime_ref(scope.new, scope.elided_static, new);
+    if old == new { Relation::Equal } else { Relation::Incompatible }
+}
+
+/// What a lifetime stands for, independent of its name.
+#[derive(PartialEq)]
+enum LifetimeRef {
+    Static,
+    /// A lifetime parameter of the generics in scope, by position
+    Param(usize),
+    /// A lifetime bound elsewhere, as by an impl or `for<'a>`
+    Other(Name),
+    /// An elided lifetime which elision does not tie to another
+    Elided,
+}
+
+fn lifetime_ref(generics: &Generics, elided_static: bool, lifetime: Option<&Lifetime>) -> LifetimeRef {
+    let lifetime = match lifetime {
+        Some(lifetime) => lifetime,
+        None if elided_static => return LifetimeRef::Static,
+        None => return LifetimeRef::Elided,
+    };
+    if &*lifetime.name.as_str() == "'static" {
+        return LifetimeRef::Static
+    }
+    match generics.lifetimes.iter().position(|def| def.lifetime.name == lifetime.name) {
+        Some(idx) => LifetimeRef::Param(idx),
+        None => LifetimeRef::Other(lifetime.name),
+    }
+}
+
+/// Name the lifetimes elided in a function signature, so that they can be
+/// matched by position like those written out.
+///
+/// Each lifetime elided in the arguments becomes a new lifetime parameter
+/// after the named ones. Those elided in the return type become the
+/// lifetime of `&self`, or of the only lifetime in the arguments.
+pub fn elaborate_lifetimes(decl: &FnDecl, generics: &Generics) -> (FnDecl, Generics) {
+    let mut inputs = ElideInputs { seen: Vec::new(), fresh: Vec::new() };
+    let args: Vec<Arg> = decl.inputs.iter().map(|arg| Arg {
+        ty: inputs.fold_ty(arg.ty.clone()),
+        pat: arg.pat.clone(),
+        id: arg.id,
+    }).collect();
+    let self_lifetime = args.first().and_then(|arg| arg.to_self()).and_then(|s| match s.node {
+        SelfKind::Region(lifetime, _) => lifetime,
+        _ => None,
+    });
+    let output_lifetime = match self_lifetime {
+        Some(lifetime) => Some(lifetime),
+        None if inputs.seen.len() == 1 => Some(inputs.seen[0]),
+        None => None,
+    };
+    let output = match (&decl.output, output_lifetime) {
+        (&FunctionRetTy::Ty(ref ty), Some(lifetime)) => FunctionRetTy::Ty(ElideOutput(lifetime).fold_ty(ty.clone())),
+        (output, _) => output.clone(),
+    };
+    let mut generics = generics.clone();
+    generics.lifetimes.extend(inputs.fresh);
+    (FnDecl { inputs: args, output: output, variadic: decl.variadic }, generics)
+}
+
+/// Names elided lifetimes in argument types, and records every lifetime.
+struct ElideInputs {
+    seen: Vec<Lifetime>,
+    fresh: Vec<LifetimeDef>,
+}
+
+impl Folder for ElideInputs {
+    fn fold_ty(&mut self, ty: P<Ty>) -> P<Ty> {
+        match ty.node {
+            // function pointers elide lifetimes on their own
+            TyKind::BareFn(..) => ty,
+            _ => fold::noop_fold_ty(ty, self),
+        }
+    }
+
+    fn fold_opt_lifetime(&mut self, lifetime: Option<Lifetime>) -> Option<Lifetime> {
+        let lifetime = lifetime.unwrap_or_else(|| {
+            let lifetime = Lifetime {
+                id: DUMMY_NODE_ID,
+                span: codemap::DUMMY_SP,
+                name: token::intern(&format!("'_{}", self.fresh.len())),
+            };
+            self.fresh.push(LifetimeDef { lifetime: lifetime, bounds: Vec::new() });
+            lifetime
+        });
+        Some(self.fold_lifetime(lifetime))
+    }
+
+    fn fold_lifetime(&mut self, lifetime: Lifetime) -> Lifetime {
+        self.seen.push(lifetime);
+        lifetime
+    }
+
+    fn fold_lifetime_def(&mut self, def: LifetimeDef) -> LifetimeDef {
+        // lifetimes bound by `for<'a>` are not argument lifetimes
+        def
+    }
+
+    fn fold_mac(&mut self, mac: Mac) -> Mac {
+        fold::noop_fold_mac(mac, self)
+    }
+}
+
+/// Fills in elided lifetimes in a return type.
+struct ElideOutput(Lifetime);
+
+impl Folder for ElideOutput {
+    fn fold_ty(&mut self, ty: P<Ty>) -> P<Ty> {
+        match ty.node {
+            TyKind::BareFn(..) => ty,
+            _ => fold::noop_fold_ty(ty, self),
+        }
+    }
+
+    fn fold_opt_lifetime(&mut self, lifetime: Option<Lifetime>) -> Option<Lifetime> {
+        Some(lifetime.unwrap_or(self.0))
+    }
+
+    fn fold_mac(&mut self, mac: Mac) -> Mac {
+        fold::noop_fold_mac(mac, self)
+    }
+}
+
+fn module_names(path: &[Name]) -> Vec<String> {
+    path.iter().map(|name| name.to_string()).collect()
+}
+
+fn strip_parens(ty: &Ty) -> &Ty {
+    match ty.node {
+        TyKind::Paren(ref inner) => strip_parens(inner),
+        _ => ty,
+    }
+}
+
+/// If this path could name a type parameter, return that name.
+fn param_name(path: &Path) -> Option<Name> {
+    if !path.global && path.segments.len() == 1 && path.segments[0].parameters.is_empty() {
+        Some(path.segments[0].identifier.name)
+    } else {
+        None
+    }
+}
+
+fn path_names(path: &Path) -> Vec<String> {
+    path.segments.iter().map(|s| s.identifier.name.to_string()).collect()
+}
+
+/// Resolve a leading `self` or `super` in a path used in `module`. Other
+/// `use` paths are already relative to the crate root.
+fn absolute(module: &[String], names: &[String]) -> Vec<String> {
+    let mut path = Vec::new();
+    let mut rest = names;
+    if names.first().map_or(false, |n| n == "self" || n == "super") {
+        path = module.to_vec();
+        while let Some((first, tail)) = rest.split_first() {
+            match &**first {
+                "self" => {}
+                "super" => { path.pop(); }
+                _ => break,
+            }
+            rest = tail;
+        }
+    }
+    path.extend(rest.iter().cloned());
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use kind::ChangeKind::*;
+    use report::Severity::*;
+    use {compare_sources, findings};
+
+    #[test]
+    fn paths_resolve_to_the_same_type() {
+        // a local type is not a foreign type of the same name
+        let r = compare_sources(
+            "pub struct Error; pub fn f(_: Error) {}",
+            "use std::io; pub struct Error; pub fn f(_: io::Error) {}");
+        assert!(findings(&r).contains(&(ArgTypeChanged, Major)));
+
+        // nor is a type of the same name in another module
+        let r = compare_sources(
+            "pub struct Foo; pub mod other { pub struct Foo; } pub fn f(_: Foo) {}",
+            "pub struct Foo; pub mod other { pub struct Foo; } pub fn f(_: other::Foo) {}");
+        assert!(findings(&r).contains(&(ArgTypeChanged, Major)));
+
+        // but a type moved behind a re-export at its old path is the same
+        let r = compare_sources(
+            "pub struct Error; pub fn f(_: Error) {}",
+            "pub use inner::Error; pub mod inner { pub struct Error; } pub fn f(_: Error) {}");
+        assert!(!findings(&r).contains(&(ArgTypeChanged, Major)));
+
+        // as are the traits of a trait object
+        let r = compare_sources(
+            "use std::fmt; pub fn f(_: Box<fmt::Debug + Send>) {}",
+            "use std::fmt::Debug; pub fn f(_: Box<Debug + ::std::marker::Send>) {}");
+        assert!(findings(&r).is_empty());
+    }
+
+    #[test]
+    fn aliases_are_scoped() {
+        // std's Result is not the crate's alias of the same name in another module
+        let r = compare_sources(
+            "pub mod error { pub struct Error; pub type Result<T> = ::std::result::Result<T, Error>; }
+             pub fn f() -> Result<(), String> { Ok(()) }",
+            "pub mod error { pub struct Error; pub type Result<T> = ::std::result::Result<T, Error>; }
+             pub fn f() -> Result<(), error::Error> { Ok(()) }");
+        assert!(findings(&r).contains(&(ReturnTypeChanged, Major)));
+
+        // the alias is expanded where it is visible, in terms of its own module
+        let r = compare_sources(
+            "pub mod error { pub struct Error; pub type Result<T> = ::std::result::Result<T, Error>; }
+             pub fn f() -> error::Result<()> { Ok(()) }",
+            "pub mod error { pub struct Error; }
+             pub fn f() -> Result<(), error::Error> { Ok(()) }");
+        assert!(!findings(&r).contains(&(ReturnTypeChanged, Major)));
+    }
+
+    #[test]
+    fn static_lifetimes() {
+        // a field or return type borrowed for less than 'static is a
+        // breaking change, not a generalization
+        let r = compare_sources(
+            "pub struct Foo<'a> { pub name: &'static str, pub other: &'a str }",
+            "pub struct Foo<'a> { pub name: &'a str, pub other: &'a str }");
+        assert!(findings(&r).contains(&(TypeChanged, Major)));
+        assert!(!findings(&r).contains(&(TypeGeneralized, Minor)));
+
+        let r = compare_sources(
+            "pub fn f<'a>(_: &'a str) -> &'static str { \"\" }",
+            "pub fn f<'a>(_: &'a str) -> &'a str { \"\" }");
+        assert!(findings(&r).contains(&(ReturnTypeChanged, Major)));
+
+        // renaming a lifetime changes nothing
+        let r = compare_sources("pub fn f<'a>(x: &'a str) -> &'a str { x }", "pub fn f<'b>(x: &'b str) -> &'b str { x }");
+        assert!(findings(&r).is_empty());
+    }
+
+    #[test]
+    fn named_lifetimes() {
+        // lifetimes are matched by position, so trading one for another is
+        // a change
+        let r = compare_sources(
+            "pub fn f<'a, 'b>(x: &'a str, _: &'b str) -> &'a str { x }",
+            "pub fn f<'a, 'b>(_: &'a str, y: &'b str) -> &'b str { y }");
+        assert!(findings(&r).contains(&(ReturnTypeChanged, Major)));
+
+        let r = compare_sources(
+            "pub struct S<'a, 'b> { pub x: &'a str, pub y: &'b str }",
+            "pub struct S<'a, 'b> { pub x: &'b str, pub y: &'a str }");
+        assert_eq!(findings(&r), vec![(TypeChanged, Major), (TypeChanged, Major)]);
+
+        let r = compare_sources(
+            "pub struct S<'a, 'b> { pub x: ::std::borrow::Cow<'a, str>, pub y: &'b str }",
+            "pub struct S<'a, 'b> { pub x: ::std::borrow::Cow<'b, str>, pub y: &'b str }");
+        assert!(findings(&r).contains(&(TypeChanged, Major)));
+    }
+
+    #[test]
+    fn elided_lifetimes() {
+        // writing out the lifetimes elision gives changes nothing
+        let r = compare_sources("pub fn f(x: &str) -> &str { x }", "pub fn f<'a>(x: &'a str) -> &'a str { x }");
+        assert!(findings(&r).is_empty());
+        let r = compare_sources(
+            "pub struct S; impl S { pub fn f(&self, _: &str) -> &u8 { &0 } }",
+            "pub struct S; impl S { pub fn f<'a, 'b>(&'a self, _: &'b str) -> &'a u8 { &0 } }");
+        assert!(findings(&r).is_empty());
+
+        // but elided lifetimes are not tied to any others
+        let r = compare_sources(
+            "pub struct S; impl S { pub fn f<'a>(&self, x: &'a str) -> &'a str { x } }",
+            "pub struct S; impl S { pub fn f<'a>(&self, x: &'a str) -> &str { x } }");
+        assert!(findings(&r).contains(&(ReturnTypeChanged, Major)));
+    }
+
+    #[test]
+    fn renamed_type_params() {
+        let r = compare_sources("pub fn f<T>(_: T) -> Option<T> { None }", "pub fn f<U>(_: U) -> Option<U> { None }");
+        assert!(findings(&r).is_empty());
+    }
+}
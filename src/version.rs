@@ -0,0 +1,197 @@
+//! suggested version numbers
+//!
+//! Cargo treats the leftmost nonzero component of a version as the one that
+//! signals incompatibility: `^0.3.1` accepts `0.3.2` but not `0.4.0`, and
+//! `^0.0.3` accepts nothing but itself. The suggested version follows the
+//! same convention, so that Cargo users pick up exactly the compatible
+//! releases.
+
+pub use semver::Version;
+use semver::Identifier;
+
+use report::*;
+
+/// The kind of release required by a set of changes.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Bump {
+    Patch,
+    Minor,
+    Major,
+}
+
+impl Bump {
+    /// The bump required by the highest change severity in a report, as
+    /// given by `Report::highest_change_severity`.
+    ///
+    /// Returns `None` for `Error`, when the comparison could not be made.
+    pub fn from_severity(severity: Severity) -> Option<Bump> {
+        match severity {
+            Severity::Debug | Severity::Note | Severity::Warning => Some(Bump::Patch),
+            // Breaking changes only require a minor version bump, per RFC 1105
+            Severity::Minor | Severity::Breaking => Some(Bump::Minor),
+            Severity::Major => Some(Bump::Major),
+            Severity::Error => None,
+        }
+    }
+
+    /// The bump between two versions, or `None` if `new` is not newer.
+    ///
+    /// Moving on from a pre-release to a later pre-release or the final
+    /// release counts as the bump the pre-release leads up to.
+    pub fn between(old: &Version, new: &Version) -> Option<Bump> {
+        if new <= old {
+            None
+        } else if old.is_prerelease() && (old.major, old.minor, old.patch) == (new.major, new.minor, new.patch) {
+            Some(prerelease_bump(old))
+        } else if significant(new) != significant(old) {
+            Some(Bump::Major)
+        } else if old.major == 0 || (new.major, new.minor) != (old.major, old.minor) {
+            // 0.x: the minor number is the significant one, so anything less
+            // is at most a patch release
+            Some(Bump::Minor)
+        } else {
+            Some(Bump::Patch)
+        }
+    }
+}
+
+/// Compute the version which should follow `current`, given the changes.
+///
+/// If `current` is a pre-release whose eventual release already satisfies
+/// the bump, the pre-release number is incremented instead.
+pub fn next_version(current: &Version, bump: Bump) -> Version {
+    let mut next = current.clone();
+    next.build.clear();
+
+    if current.is_prerelease() && prerelease_bump(current) >= bump {
+        increment_prerelease(&mut next);
+        return next
+    }
+
+    match (current.major, current.minor, bump) {
+        // 0.0.x: every release is incompatible with every other, so only the
+        // patch number moves; in 0.x, the minor number signals incompatibility
+        (0, 0, _) | (0, _, Bump::Minor) | (_, _, Bump::Patch) => next.increment_patch(),
+        (0, _, Bump::Major) | (_, _, Bump::Minor) => next.increment_minor(),
+        (_, _, Bump::Major) => next.increment_major(),
+    }
+    next
+}
+
+/// Suggest the version which should follow `current`, given the highest
+/// change severity in the report.
+pub fn suggest(current: &Version, severity: Severity) -> Option<Version> {
+    Bump::from_severity(severity).map(|bump| next_version(current, bump))
+}
+
+/// Check that the bump from `old` to `new` is enough for the changes in the
+/// report, adding an `Error` if it is not.
+pub fn verify(r: &mut Report, old: &Version, new: &Version) {
+    let required = match Bump::from_severity(r.highest_change_severity()) {
+        Some(bump) => bump,
+        // the comparison itself failed, which has already been reported
+        None => return,
+    };
+    let suggested = next_version(old, required);
+    match Bump::between(old, new) {
+        Some(actual) if actual >= required => {
+            push!(r, Note, "version bumped {} \u{2192} {}, which is sufficient", old, new);
+        }
+        Some(_) => {
+            push!(r, Error(VersionBumpInsufficient), "version bumped {} \u{2192} {} but changes require {}", old, new, suggested);
+        }
+        None if old == new && required == Bump::Patch => {
+            push!(r, Note, "version unchanged at {}", old);
+        }
+        None => {
+            push!(r, Error(VersionBumpInsufficient), "version changed {} \u{2192} {} but changes require {}", old, new, suggested);
+        }
+    }
+}
+
+/// The kind of release a pre-release version leads up to.
+fn prerelease_bump(version: &Version) -> Bump {
+    match (version.major, version.minor, version.patch) {
+        (0, 0, _) | (0, _, 0) | (_, 0, 0) => Bump::Major,
+        (0, _, _) | (_, _, 0) => Bump::Minor,
+        _ => Bump::Patch,
+    }
+}
+
+/// Increment the last numeric pre-release identifier, or append one.
+fn increment_prerelease(version: &mut Version) {
+    if let Some(&mut Identifier::Numeric(ref mut n)) = version.pre.last_mut() {
+        *n += 1;
+        return
+    }
+    version.pre.push(Identifier::Numeric(1));
+}
+
+/// The components of a version which Cargo considers for compatibility.
+fn significant(version: &Version) -> (u64, u64, u64) {
+    match (version.major, version.minor) {
+        (0, 0) => (0, 0, version.patch),
+        (0, minor) => (0, minor, 0),
+        (major, _) => (major, 0, 0),
+    }
+}
+
+#[test]
+fn next_versions() {
+    fn next(current: &str, bump: Bump) -> String {
+        next_version(&Version::parse(current).unwrap(), bump).to_string()
+    }
+
+    assert_eq!(next("1.2.3", Bump::Patch), "1.2.4");
+    assert_eq!(next("1.2.3", Bump::Minor), "1.3.0");
+    assert_eq!(next("1.2.3", Bump::Major), "2.0.0");
+    assert_eq!(next("0.3.1", Bump::Minor), "0.3.2");
+    assert_eq!(next("0.3.1", Bump::Major), "0.4.0");
+    assert_eq!(next("0.0.7", Bump::Major), "0.0.8");
+    assert_eq!(next("2.0.0-beta.1", Bump::Major), "2.0.0-beta.2");
+    assert_eq!(next("2.0.0-rc", Bump::Minor), "2.0.0-rc.1");
+    assert_eq!(next("1.2.3-beta.1", Bump::Minor), "1.3.0");
+    assert_eq!(next("0.4.0-alpha.2", Bump::Major), "0.4.0-alpha.3");
+}
+
+#[test]
+fn warnings_do_not_hide_changes() {
+    let mut r = Report::new();
+    {
+        let arg = push!(r, Minor(ArgGeneralized));
+        push!(arg, Warning, "Could not check bounds");
+    }
+    push!(r, Warning, "Could not parse macro arms");
+    assert_eq!(r.highest_severity(), Severity::Warning);
+    assert_eq!(Bump::from_severity(r.highest_change_severity()), Some(Bump::Minor));
+}
+
+#[test]
+fn verified_bumps() {
+    use kind::ChangeKind;
+
+    // whether `verify` accepts the bump for a report with the given change
+    fn sufficient<F: Fn(&mut Report)>(old: &str, new: &str, change: F) -> bool {
+        let mut r = Report::new();
+        change(&mut r);
+        verify(&mut r, &Version::parse(old).unwrap(), &Version::parse(new).unwrap());
+        !r.children.iter().any(|child| child.item.kind == Some(ChangeKind::VersionBumpInsufficient))
+    }
+    let major = |r: &mut Report| { push!(r, Major(ItemRemoved)); };
+    let minor = |r: &mut Report| { push!(r, Minor(ItemAdded)); };
+    let none = |_: &mut Report| {};
+
+    // under-bumped
+    assert!(!sufficient("1.2.3", "1.3.0", &major));
+    assert!(!sufficient("1.2.3", "1.2.4", &minor));
+    assert!(!sufficient("1.2.3", "1.2.3", &minor));
+    // over-bumped is fine
+    assert!(sufficient("1.2.3", "2.0.0", &minor));
+    assert!(sufficient("1.2.3", "1.3.0", &none));
+    assert!(sufficient("1.2.3", "1.2.3", &none));
+    // 0.x: the minor number is the significant one
+    assert!(!sufficient("0.3.1", "0.3.2", &major));
+    assert!(sufficient("0.3.1", "0.4.0", &major));
+    assert!(sufficient("0.3.1", "0.3.2", &minor));
+    assert!(!sufficient("0.0.1", "0.0.1", &minor));
+}